@@ -1,7 +1,14 @@
+#[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+use crate::input_log::{InputLog, InputRecorder, InputReplay, LoggedInput};
 use crate::{
+    clock::Clock,
+    config::RunnerConfig,
     context::GraphicsContext,
     controller::ControllerTrait,
+    key_bindings::{KeyAction, KeyBindings, KeyInput},
     render_pass::RenderPass,
+    shader_modules::ShaderModules,
+    transfer::{self, DroppedFile},
     ui::{Ui, UiState},
     user_event::CustomEvent,
 };
@@ -9,14 +16,15 @@ use crate::{
 use egui_winit::winit::platform::wayland::*;
 use egui_winit::winit::{
     application::ApplicationHandler,
-    dpi::{PhysicalPosition, PhysicalSize},
-    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
+    event::{
+        ElementState, KeyEvent, Modifiers, MouseButton, MouseScrollDelta, Touch, TouchPhase,
+        WindowEvent,
+    },
     event_loop::{ActiveEventLoop, EventLoopProxy},
-    keyboard::{Key, NamedKey},
-    window::{Window, WindowId},
+    window::{Fullscreen, Window, WindowId},
 };
-use std::borrow::Cow;
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 pub struct Graphics<C: ControllerTrait> {
     rpass: RenderPass,
@@ -24,13 +32,114 @@ pub struct Graphics<C: ControllerTrait> {
     controller: C,
     ui: Ui,
     ui_state: UiState,
+    key_bindings: KeyBindings,
+    #[cfg(not(target_arch = "wasm32"))]
+    modifiers: egui_winit::winit::keyboard::ModifiersState,
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    input_log: Option<InputLog>,
     window: Arc<Window>,
 }
 
+impl<C: ControllerTrait> Graphics<C> {
+    fn key_input(&mut self, event_loop: &ActiveEventLoop, key: KeyInput) {
+        let Some(action) = self.key_bindings.action(&key.logical_key) else {
+            self.controller.keyboard_input(key);
+            return;
+        };
+        if key.state != ElementState::Pressed || key.repeat {
+            return;
+        }
+        match action {
+            KeyAction::Quit => event_loop.exit(),
+            KeyAction::ToggleUi => self.ui_state.show_ui = !self.ui_state.show_ui,
+            #[cfg(not(target_arch = "wasm32"))]
+            KeyAction::Screenshot => self.ui_state.screenshot(false),
+            KeyAction::TogglePause => {
+                let clock = &mut self.ui_state.clock;
+                clock.set_paused(!clock.is_paused());
+            }
+            KeyAction::Step => self.ui_state.clock.step(),
+            KeyAction::ToggleFullscreen => {
+                let fullscreen = match self.window.fullscreen() {
+                    Some(_) => None,
+                    None => Some(Fullscreen::Borderless(None)),
+                };
+                self.window.set_fullscreen(fullscreen);
+            }
+        }
+    }
+
+    /// Record live input, false while replaying since live input is ignored then
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    fn log_input(&mut self, input: LoggedInput) -> bool {
+        self.log_input_at(self.ui_state.clock.frames(), input)
+    }
+
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    fn log_input_at(&mut self, frame: u64, input: LoggedInput) -> bool {
+        match &mut self.input_log {
+            Some(InputLog::Replaying(_)) => false,
+            Some(InputLog::Recording(recorder)) => {
+                if let Err(err) = recorder.record(frame, input) {
+                    log::error!("Stopped recording input: {err}");
+                    self.input_log = None;
+                }
+                true
+            }
+            None => true,
+        }
+    }
+
+    /// Deliver the input recorded before the next frame, returns the real seconds that frame took
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    fn replay_frame(&mut self, event_loop: &ActiveEventLoop) -> Option<f32> {
+        let Some(InputLog::Replaying(replay)) = &mut self.input_log else {
+            return None;
+        };
+        let Some(frame) = replay.next_frame() else {
+            log::info!("Input replay finished, taking live input again");
+            self.input_log = None;
+            self.ui_state.replayed_pointer_over_viewport = None;
+            return None;
+        };
+        self.ui_state.replayed_pointer_over_viewport = Some(frame.pointer_over_viewport);
+        for input in frame.inputs {
+            match input {
+                LoggedInput::Frame { .. } => {}
+                LoggedInput::Resize { width, height } => {
+                    let _ = self
+                        .window
+                        .request_inner_size(PhysicalSize::new(width, height));
+                }
+                LoggedInput::MouseMove { x, y } => self.controller.mouse_move(glam::dvec2(x, y)),
+                LoggedInput::MouseScroll { x, y } => {
+                    self.controller.mouse_scroll(glam::dvec2(x, y))
+                }
+                LoggedInput::MouseInput { state, button } => {
+                    self.controller.mouse_input(state, button)
+                }
+                LoggedInput::Keyboard { key } => self.key_input(event_loop, key),
+                LoggedInput::Modifiers { state } => {
+                    self.modifiers = state;
+                    self.controller.modifiers_changed(state);
+                }
+                LoggedInput::Touch { id, phase, x, y } => {
+                    self.controller.touch(id, phase, glam::dvec2(x, y))
+                }
+                LoggedInput::CursorEntered => self.controller.cursor_entered(),
+                LoggedInput::CursorLeft => self.controller.cursor_left(),
+                LoggedInput::Focused { focused } => self.controller.focused(focused),
+            }
+        }
+        Some(frame.delta)
+    }
+}
+
 pub struct Builder<C: ControllerTrait> {
     event_proxy: EventLoopProxy<CustomEvent<C>>,
-    shader_bytes: Cow<'static, [u8]>,
+    shader: ShaderModules<'static>,
     controller: C,
+    config: RunnerConfig,
 }
 
 pub enum App<C: ControllerTrait> {
@@ -42,13 +151,15 @@ pub enum App<C: ControllerTrait> {
 impl<'a, C: ControllerTrait> App<C> {
     pub fn new(
         event_proxy: EventLoopProxy<CustomEvent<C>>,
-        shader_bytes: Cow<'static, [u8]>,
+        shader: ShaderModules<'static>,
         controller: C,
+        config: RunnerConfig,
     ) -> Self {
         Self::Builder(Builder {
             event_proxy,
-            shader_bytes,
+            shader,
             controller,
+            config,
         })
     }
 
@@ -60,24 +171,49 @@ impl<'a, C: ControllerTrait> App<C> {
             }
             return;
         };
+        // The surface follows the window even while replaying
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        gfx.log_input(LoggedInput::Resize {
+            width: size.width,
+            height: size.height,
+        });
         if size.width != 0 && size.height != 0 {
             gfx.ctx.config.width = size.width;
             gfx.ctx.config.height = size.height;
-            gfx.ctx.surface.configure(&gfx.ctx.device, &gfx.ctx.config);
+            gfx.ctx.configure_surface();
         }
     }
 
-    pub fn keyboard_input(&mut self, event: KeyEvent) {
+    pub fn keyboard_input(&mut self, event_loop: &ActiveEventLoop, event: KeyEvent) {
         let Self::Graphics(gfx) = self else {
             return;
         };
-        gfx.controller.keyboard_input(event);
+        #[cfg(not(target_arch = "wasm32"))]
+        if event.state == ElementState::Pressed
+            && !event.repeat
+            && transfer::is_paste(&event, gfx.modifiers)
+        {
+            if let Some(paste) = transfer::read_clipboard() {
+                gfx.controller.paste(paste);
+                return;
+            }
+        }
+        let key = KeyInput::from(event);
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::Keyboard { key: key.clone() }) {
+            return;
+        }
+        gfx.key_input(event_loop, key);
     }
 
     pub fn mouse_input(&mut self, state: ElementState, button: MouseButton) {
         let Self::Graphics(gfx) = self else {
             return;
         };
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::MouseInput { state, button }) {
+            return;
+        }
         gfx.controller.mouse_input(state, button);
     }
 
@@ -86,9 +222,133 @@ impl<'a, C: ControllerTrait> App<C> {
             return;
         };
         let position = glam::dvec2(position.x, position.y) - gfx.rpass.shader_offset().as_dvec2();
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::MouseMove {
+            x: position.x,
+            y: position.y,
+        }) {
+            return;
+        }
         gfx.controller.mouse_move(position);
     }
 
+    pub fn touch(&mut self, touch: Touch) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        let position =
+            glam::dvec2(touch.location.x, touch.location.y) - gfx.rpass.shader_offset().as_dvec2();
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::Touch {
+            id: touch.id,
+            phase: touch.phase,
+            x: position.x,
+            y: position.y,
+        }) {
+            return;
+        }
+        gfx.controller.touch(touch.id, touch.phase, position);
+    }
+
+    pub fn pinch_gesture(&mut self, delta: f64, phase: TouchPhase) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        gfx.controller.pinch_gesture(delta, phase);
+    }
+
+    pub fn pan_gesture(&mut self, delta: PhysicalPosition<f32>, phase: TouchPhase) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        gfx.controller
+            .pan_gesture(glam::vec2(delta.x, delta.y), phase);
+    }
+
+    pub fn rotation_gesture(&mut self, delta: f32, phase: TouchPhase) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        gfx.controller.rotation_gesture(delta, phase);
+    }
+
+    pub fn modifiers_changed(&mut self, modifiers: Modifiers) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::Modifiers {
+            state: modifiers.state(),
+        }) {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            gfx.modifiers = modifiers.state();
+        }
+        gfx.controller.modifiers_changed(modifiers.state());
+    }
+
+    pub fn file_dropped(&mut self, file: DroppedFile) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        gfx.controller.file_dropped(file);
+        gfx.window.request_redraw();
+    }
+
+    pub fn file_hovered(&mut self, path: Option<PathBuf>) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        gfx.controller.file_hovered(path);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn paste(&mut self, paste: transfer::Paste) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        // Natively egui takes the key event instead
+        if gfx.ui.wants_keyboard_input() {
+            return;
+        }
+        gfx.controller.paste(paste);
+    }
+
+    pub fn cursor_entered(&mut self) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::CursorEntered) {
+            return;
+        }
+        gfx.controller.cursor_entered();
+    }
+
+    pub fn cursor_left(&mut self) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::CursorLeft) {
+            return;
+        }
+        gfx.controller.cursor_left();
+    }
+
+    pub fn focused(&mut self, focused: bool) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::Focused { focused }) {
+            return;
+        }
+        gfx.controller.focused(focused);
+    }
+
     pub fn mouse_scroll(&mut self, delta: MouseScrollDelta) {
         let Self::Graphics(gfx) = self else {
             return;
@@ -99,6 +359,13 @@ impl<'a, C: ControllerTrait> App<C> {
         };
         #[cfg(target_arch = "wasm32")]
         let delta = delta * 0.2777778;
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        if !gfx.log_input(LoggedInput::MouseScroll {
+            x: delta.x,
+            y: delta.y,
+        }) {
+            return;
+        }
         gfx.controller.mouse_scroll(delta);
     }
 
@@ -113,20 +380,47 @@ impl<'a, C: ControllerTrait> App<C> {
             .and_then(|m| m.refresh_rate_millihertz().map(|x| x as f32 / 1000.0))
             .unwrap_or(60.0)
             .recip();
+        // Every frame is kept while recording, so there's no reason to drop simulation steps
+        #[cfg(not(target_arch = "wasm32"))]
+        let frame_time = if gfx.ui_state.is_recording() {
+            f32::INFINITY
+        } else {
+            frame_time
+        };
+        let time = gfx.ui_state.clock.frame_time();
         gfx.controller.update(
-            |dimensions, threads, push_constants| {
-                gfx.rpass
-                    .compute(&gfx.ctx, dimensions, threads, push_constants);
+            |entry, dimensions, threads, push_constants| {
+                gfx.rpass.compute(
+                    &gfx.ctx,
+                    entry,
+                    dimensions,
+                    threads,
+                    bytemuck::bytes_of(push_constants),
+                );
             },
             frame_time,
+            time,
         );
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    pub fn replay_frame(&mut self, event_loop: &ActiveEventLoop) -> Option<f32> {
+        let Self::Graphics(gfx) = self else {
+            return None;
+        };
+        gfx.replay_frame(event_loop)
+    }
+
+    /// `replayed` is the length of the frame when replaying input
+    pub fn render(&mut self, replayed: Option<f32>) -> Result<(), wgpu::SurfaceError> {
         let Self::Graphics(gfx) = self else {
             return Ok(());
         };
         gfx.window.request_redraw();
+        let delta = gfx.ui_state.frame_delta(replayed);
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        let frame = gfx.ui_state.clock.frames();
+        gfx.ui_state.clock.tick(delta);
         let result = gfx.rpass.render(
             &gfx.ctx,
             &gfx.window,
@@ -134,6 +428,14 @@ impl<'a, C: ControllerTrait> App<C> {
             &mut gfx.ui_state,
             &mut gfx.controller,
         );
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        gfx.log_input_at(
+            frame,
+            LoggedInput::Frame {
+                delta,
+                pointer_over_viewport: gfx.ui_state.pointer_over_viewport(),
+            },
+        );
         #[cfg(not(target_arch = "wasm32"))]
         gfx.ctx.set_vsync(gfx.ui_state.vsync);
         result
@@ -147,11 +449,26 @@ impl<'a, C: ControllerTrait> App<C> {
     }
 
     #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
-    pub fn new_module(&mut self, shader_path: &std::path::Path) {
+    pub fn new_module(&mut self, shader: &ShaderModules) {
+        let Self::Graphics(gfx) = self else {
+            return;
+        };
+        match gfx.rpass.new_module(&gfx.ctx, shader, &mut gfx.controller) {
+            Ok(()) => gfx.ui_state.shader_error = None,
+            Err(err) => {
+                log::error!("Keeping the previous shader: {err}");
+                gfx.ui_state.shader_error = Some(err.to_string());
+            }
+        }
+        gfx.window.request_redraw();
+    }
+
+    #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+    pub fn shader_error(&mut self, error: String) {
         let Self::Graphics(gfx) = self else {
             return;
         };
-        gfx.rpass.new_module(&gfx.ctx, shader_path);
+        gfx.ui_state.shader_error = Some(error);
         gfx.window.request_redraw();
     }
 }
@@ -165,14 +482,23 @@ impl<C: ControllerTrait> ApplicationHandler<CustomEvent<C>> for App<C> {
                 None,
             ),
         ) {
-            let window_attributes = Window::default_attributes().with_title(crate::TITLE);
+            let config = &builder.config;
+            let mut window_attributes = Window::default_attributes().with_title(&config.title);
+            if let Some(size) = config.size {
+                window_attributes =
+                    window_attributes.with_inner_size(LogicalSize::new(size.x, size.y));
+            }
+            if config.fullscreen {
+                window_attributes =
+                    window_attributes.with_fullscreen(Some(Fullscreen::Borderless(None)));
+            }
             let window_attributes = {
                 cfg_if::cfg_if! {
                     if #[cfg(target_arch = "wasm32")] {
                         use egui_winit::winit::platform::web::WindowAttributesExtWebSys;
                         window_attributes.with_append(true)
                     } else {
-                        window_attributes.with_name(crate::TITLE, "")
+                        window_attributes.with_name(&config.title, "")
                     }
                 }
             };
@@ -180,6 +506,10 @@ impl<C: ControllerTrait> ApplicationHandler<CustomEvent<C>> for App<C> {
 
             cfg_if::cfg_if! {
                 if #[cfg(target_arch = "wasm32")] {
+                    use egui_winit::winit::platform::web::WindowExtWebSys;
+                    if let Some(canvas) = window.canvas() {
+                        transfer::listen(&canvas, builder.event_proxy.clone());
+                    }
                     let size = web_sys::window()
                         .map(|win| {
                             win.document()
@@ -212,27 +542,35 @@ impl<C: ControllerTrait> ApplicationHandler<CustomEvent<C>> for App<C> {
         }
         match event {
             WindowEvent::RedrawRequested => {
+                #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+                let replayed = self.replay_frame(event_loop);
+                #[cfg(not(all(feature = "input-recording", not(target_arch = "wasm32"))))]
+                let replayed = None;
                 #[cfg(feature = "compute")]
-                if let Err(wgpu::SurfaceError::OutOfMemory) = self.render() {
+                if let Err(wgpu::SurfaceError::OutOfMemory) = self.render(replayed) {
                     event_loop.exit()
                 }
                 self.update();
             }
-            WindowEvent::CloseRequested
-            | WindowEvent::KeyboardInput {
-                event:
-                    KeyEvent {
-                        state: ElementState::Pressed,
-                        logical_key: Key::Named(NamedKey::Escape),
-                        ..
-                    },
-                ..
-            } => event_loop.exit(),
-            WindowEvent::KeyboardInput { event, .. } => self.keyboard_input(event),
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::KeyboardInput { event, .. } => self.keyboard_input(event_loop, event),
             WindowEvent::Resized(size) => self.resize(size),
             WindowEvent::MouseInput { state, button, .. } => self.mouse_input(state, button),
             WindowEvent::MouseWheel { delta, .. } => self.mouse_scroll(delta),
             WindowEvent::CursorMoved { position, .. } => self.mouse_move(position),
+            WindowEvent::CursorEntered { .. } => self.cursor_entered(),
+            WindowEvent::CursorLeft { .. } => self.cursor_left(),
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers_changed(modifiers),
+            WindowEvent::Focused(focused) => self.focused(focused),
+            WindowEvent::DroppedFile(path) => self.file_dropped(DroppedFile::Path(path)),
+            WindowEvent::HoveredFile(path) => self.file_hovered(Some(path)),
+            WindowEvent::HoveredFileCancelled => self.file_hovered(None),
+            WindowEvent::Touch(touch) => self.touch(touch),
+            WindowEvent::PinchGesture { delta, phase, .. } => self.pinch_gesture(delta, phase),
+            WindowEvent::PanGesture { delta, phase, .. } => self.pan_gesture(delta, phase),
+            WindowEvent::RotationGesture { delta, phase, .. } => {
+                self.rotation_gesture(delta, phase)
+            }
             _ => {}
         }
     }
@@ -252,7 +590,13 @@ impl<C: ControllerTrait> ApplicationHandler<CustomEvent<C>> for App<C> {
                 };
             }
             #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
-            CustomEvent::NewModule(shader_path) => self.new_module(&shader_path),
+            CustomEvent::NewModule(shader) => self.new_module(&shader),
+            #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+            CustomEvent::ShaderError(error) => self.shader_error(error),
+            #[cfg(target_arch = "wasm32")]
+            CustomEvent::FileDropped(file) => self.file_dropped(file),
+            #[cfg(target_arch = "wasm32")]
+            CustomEvent::Paste(paste) => self.paste(paste),
         }
     }
 }
@@ -263,14 +607,26 @@ async fn create_graphics<C: ControllerTrait>(
     window: Window,
 ) {
     let window = Arc::new(window);
-    let ctx = GraphicsContext::new(window.clone(), initial_size).await;
+    let ctx = GraphicsContext::new(window.clone(), initial_size, &builder.config).await;
 
     let ui = Ui::new(window.clone());
 
-    let ui_state = UiState::new();
+    let mut ui_state = UiState::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        ui_state.vsync = ctx.vsync();
+        ui_state.can_capture = ctx.config.usage.contains(wgpu::TextureUsages::COPY_SRC);
+    }
+    ui_state.clock = Clock::new(builder.config.timestep, builder.config.paused);
 
     let mut controller = builder.controller;
-    let rpass = RenderPass::new(&ctx, &builder.shader_bytes, &mut controller);
+    let key_bindings = controller.key_bindings();
+    let rpass = RenderPass::new(
+        &ctx,
+        &builder.shader,
+        &mut controller,
+        builder.config.clear_color,
+    );
 
     let gfx = Graphics {
         rpass,
@@ -278,6 +634,11 @@ async fn create_graphics<C: ControllerTrait>(
         controller,
         ui,
         ui_state,
+        key_bindings,
+        #[cfg(not(target_arch = "wasm32"))]
+        modifiers: Default::default(),
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        input_log: open_input_log(&builder.config),
         window,
     };
 
@@ -286,3 +647,30 @@ async fn create_graphics<C: ControllerTrait>(
         .send_event(CustomEvent::CreateWindow(gfx))
         .ok();
 }
+
+#[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+fn open_input_log(config: &RunnerConfig) -> Option<InputLog> {
+    if let Some(path) = &config.replay_input {
+        if config.record_input.is_some() {
+            log::warn!(
+                "Replaying input from {}, so none is recorded",
+                path.display()
+            );
+        }
+        return match InputReplay::open(path) {
+            Ok(replay) => Some(InputLog::Replaying(replay)),
+            Err(err) => {
+                log::error!("Failed to open input replay {}: {err}", path.display());
+                None
+            }
+        };
+    }
+    let path = config.record_input.as_ref()?;
+    match InputRecorder::create(path) {
+        Ok(recorder) => Some(InputLog::Recording(recorder)),
+        Err(err) => {
+            log::error!("Failed to record input to {}: {err}", path.display());
+            None
+        }
+    }
+}
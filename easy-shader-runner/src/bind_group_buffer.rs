@@ -1,6 +1,191 @@
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
 pub struct BufferDescriptor<'a> {
+    /// Initial contents, for textures these are tightly packed rows of texels
+    /// Textures are zero initialized when empty
     pub data: &'a [u8],
     pub read_only: bool,
     pub shader_stages: wgpu::ShaderStages,
     pub cpu_writable: bool,
+    pub kind: BindingKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BindingKind {
+    StorageBuffer,
+    UniformBuffer,
+    Texture {
+        size: glam::UVec2,
+        format: wgpu::TextureFormat,
+    },
+    StorageTexture {
+        size: glam::UVec2,
+        format: wgpu::TextureFormat,
+        access: wgpu::StorageTextureAccess,
+    },
+    /// Any other binding in the set can use this to sample a [`BindingKind::Texture`]
+    Sampler {
+        filter: wgpu::FilterMode,
+        address_mode: wgpu::AddressMode,
+    },
+}
+
+impl BufferDescriptor<'_> {
+    /// Resources are kept across [`crate::ControllerTrait::describe_buffers`] calls while this stays the same
+    pub(crate) fn key(&self) -> ResourceKey {
+        ResourceKey {
+            kind: self.kind,
+            read_only: self.read_only,
+            shader_stages: self.shader_stages,
+            cpu_writable: self.cpu_writable,
+            data_len: self.data.len(),
+        }
+    }
+
+    pub(crate) fn create_resource(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> BoundResource {
+        let texture_descriptor = |size: glam::UVec2, format, usage| wgpu::TextureDescriptor {
+            label: Some("Bind Group Texture"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        };
+        let create_texture = |descriptor: &wgpu::TextureDescriptor| {
+            let texture = if self.data.is_empty() {
+                device.create_texture(descriptor)
+            } else {
+                device.create_texture_with_data(
+                    queue,
+                    descriptor,
+                    wgpu::util::TextureDataOrder::LayerMajor,
+                    self.data,
+                )
+            };
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            BoundResource::Texture(Arc::new(texture), Arc::new(view))
+        };
+        match self.kind {
+            BindingKind::StorageBuffer | BindingKind::UniformBuffer => {
+                let mut usage = if let BindingKind::UniformBuffer = self.kind {
+                    wgpu::BufferUsages::UNIFORM
+                } else {
+                    wgpu::BufferUsages::STORAGE
+                };
+                if self.cpu_writable {
+                    usage |= wgpu::BufferUsages::COPY_DST;
+                }
+                // For `HeadlessRunner::read_buffer`, the usage costs nothing in a window
+                usage |= wgpu::BufferUsages::COPY_SRC;
+                BoundResource::Buffer(Arc::new(device.create_buffer_init(
+                    &wgpu::util::BufferInitDescriptor {
+                        label: Some("Bind Group Buffer"),
+                        contents: self.data,
+                        usage,
+                    },
+                )))
+            }
+            BindingKind::Texture { size, format } => {
+                let mut usage = wgpu::TextureUsages::TEXTURE_BINDING;
+                if self.cpu_writable {
+                    usage |= wgpu::TextureUsages::COPY_DST;
+                }
+                create_texture(&texture_descriptor(size, format, usage))
+            }
+            BindingKind::StorageTexture { size, format, .. } => {
+                let mut usage = wgpu::TextureUsages::STORAGE_BINDING;
+                if self.cpu_writable {
+                    usage |= wgpu::TextureUsages::COPY_DST;
+                }
+                create_texture(&texture_descriptor(size, format, usage))
+            }
+            BindingKind::Sampler {
+                filter,
+                address_mode,
+            } => {
+                BoundResource::Sampler(Arc::new(device.create_sampler(&wgpu::SamplerDescriptor {
+                    label: Some("Bind Group Sampler"),
+                    address_mode_u: address_mode,
+                    address_mode_v: address_mode,
+                    address_mode_w: address_mode,
+                    mag_filter: filter,
+                    min_filter: filter,
+                    ..Default::default()
+                })))
+            }
+        }
+    }
+
+    pub(crate) fn binding_type(&self) -> wgpu::BindingType {
+        match self.kind {
+            BindingKind::StorageBuffer => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage {
+                    read_only: self.read_only,
+                },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            BindingKind::UniformBuffer => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            BindingKind::Texture { format, .. } => wgpu::BindingType::Texture {
+                sample_type: format
+                    .sample_type(None, None)
+                    .expect("texture format can't be sampled"),
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            BindingKind::StorageTexture { format, access, .. } => {
+                wgpu::BindingType::StorageTexture {
+                    access,
+                    format,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                }
+            }
+            BindingKind::Sampler { filter, .. } => wgpu::BindingType::Sampler(match filter {
+                wgpu::FilterMode::Linear => wgpu::SamplerBindingType::Filtering,
+                wgpu::FilterMode::Nearest => wgpu::SamplerBindingType::NonFiltering,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) struct ResourceKey {
+    kind: BindingKind,
+    read_only: bool,
+    shader_stages: wgpu::ShaderStages,
+    cpu_writable: bool,
+    data_len: usize,
+}
+
+/// Shared so bind groups can be rebuilt without giving up the ones in use
+#[derive(Clone)]
+pub(crate) enum BoundResource {
+    Buffer(Arc<wgpu::Buffer>),
+    Texture(Arc<wgpu::Texture>, Arc<wgpu::TextureView>),
+    Sampler(Arc<wgpu::Sampler>),
+}
+
+impl BoundResource {
+    pub fn as_binding_resource(&self) -> wgpu::BindingResource<'_> {
+        match self {
+            Self::Buffer(buffer) => buffer.as_entire_binding(),
+            Self::Texture(_, view) => wgpu::BindingResource::TextureView(view),
+            Self::Sampler(sampler) => wgpu::BindingResource::Sampler(sampler),
+        }
+    }
 }
@@ -0,0 +1,213 @@
+use crate::context::GraphicsContext;
+
+/// Tightly packed RGBA8 pixels read back from the GPU
+#[derive(Clone)]
+pub struct Image {
+    pub size: glam::UVec2,
+    pub data: Vec<u8>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Image {
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> Result<(), png::EncodingError> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.size.x, self.size.y);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.data)
+    }
+
+    /// Read a PNG of any 8 or 16 bit color type, converting it to RGBA8
+    pub fn load_png(path: impl AsRef<std::path::Path>) -> Result<Self, png::DecodingError> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let pixels = &buf[..info.buffer_size()];
+        let data = match info.color_type {
+            png::ColorType::Rgba => pixels.to_vec(),
+            png::ColorType::Rgb => pixels
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => pixels
+                .chunks_exact(2)
+                .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+                .collect(),
+            png::ColorType::Grayscale => pixels.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            // Expanded to RGB by `normalize_to_color8`
+            png::ColorType::Indexed => unreachable!(),
+        };
+        Ok(Self {
+            size: glam::uvec2(info.width, info.height),
+            data,
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct ScreenshotRequest {
+    pub path: std::path::PathBuf,
+    pub include_ui: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ScreenshotRequest {
+    pub fn new(path: Option<std::path::PathBuf>, include_ui: bool) -> Self {
+        let path = path.unwrap_or_else(|| {
+            let millis = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            format!("screenshot_{millis}.png").into()
+        });
+        Self { path, include_ui }
+    }
+
+    pub fn save(self, ctx: &GraphicsContext, texture: &wgpu::Texture, rect: egui::Rect) {
+        let Some(image) = read_viewport(ctx, texture, rect) else {
+            return;
+        };
+        match image.save_png(&self.path) {
+            Ok(()) => log::info!("Saved screenshot to {}", self.path.display()),
+            Err(err) => log::error!("Failed to save screenshot: {err}"),
+        }
+    }
+}
+
+/// Read `rect` (in physical pixels) from `texture`
+/// Returns `None` if the texture can't be copied from or `rect` is empty
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_viewport(
+    ctx: &GraphicsContext,
+    texture: &wgpu::Texture,
+    rect: egui::Rect,
+) -> Option<Image> {
+    if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+        log::error!("Capturing frames is not supported by this surface");
+        return None;
+    }
+    let texture_size = glam::uvec2(texture.width(), texture.height());
+    let origin = glam::vec2(rect.left(), rect.top())
+        .max(glam::Vec2::ZERO)
+        .as_uvec2()
+        .min(texture_size);
+    let size = glam::vec2(rect.width(), rect.height())
+        .as_uvec2()
+        .min(texture_size - origin);
+    if size.x == 0 || size.y == 0 {
+        return None;
+    }
+    Some(read_texture_region(ctx, texture, origin, size))
+}
+
+/// Copy `texture` into a buffer and block until it can be read on the cpu
+/// Supports 8 bit RGBA and BGRA formats
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_texture(ctx: &GraphicsContext, texture: &wgpu::Texture) -> Image {
+    let size = glam::uvec2(texture.width(), texture.height());
+    read_texture_region(ctx, texture, glam::UVec2::ZERO, size)
+}
+
+/// Copy `buffer` back from the GPU, it needs [`wgpu::BufferUsages::COPY_SRC`]
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_buffer(ctx: &GraphicsContext, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let staging = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: buffer.size(),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = staging.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    ctx.device.poll(wgpu::Maintain::Wait);
+    let data = slice.get_mapped_range().to_vec();
+    staging.unmap();
+    data
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_texture_region(
+    ctx: &GraphicsContext,
+    texture: &wgpu::Texture,
+    origin: glam::UVec2,
+    size: glam::UVec2,
+) -> Image {
+    let unpadded_bytes_per_row = size.x * 4;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row * size.y) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = ctx
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: origin.x,
+                y: origin.y,
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(size.y),
+            },
+        },
+        wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+    );
+    ctx.queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    ctx.device.poll(wgpu::Maintain::Wait);
+
+    let mut data = Vec::with_capacity((unpadded_bytes_per_row * size.y) as usize);
+    for row in slice
+        .get_mapped_range()
+        .chunks_exact(padded_bytes_per_row as usize)
+    {
+        data.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+    }
+    buffer.unmap();
+
+    if matches!(
+        texture.format(),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    ) {
+        for pixel in data.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    Image { size, data }
+}
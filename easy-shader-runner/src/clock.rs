@@ -0,0 +1,231 @@
+use web_time::Instant;
+
+pub(crate) const DEFAULT_TIMESTEP: f32 = 1.0 / 60.0;
+
+/// Wall clock frames longer than this are cut short, e.g. after the window was hidden or dragged
+const MAX_DELTA: f32 = 0.25;
+
+/// Steps beyond this are dropped, so a high speed or tiny timestep can't stall a frame
+const MAX_STEPS_PER_FRAME: u32 = 1000;
+
+pub(crate) fn check_timestep(timestep: f32) {
+    assert!(
+        timestep > 0.0 && timestep.is_finite(),
+        "The timestep must be a positive number of seconds, got {timestep}"
+    );
+}
+
+/// The simulation time of a frame, passed to [`crate::ControllerTrait::prepare_render`] and [`crate::ControllerTrait::update`]
+/// Advance simulations by this instead of measuring time yourself, so recordings and headless renders are reproducible
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTime {
+    /// Frames before this one
+    pub frame: u64,
+    /// Simulated seconds since the previous frame, scaled by [`Clock::speed`] and zero while paused
+    pub delta: f32,
+    /// Simulated seconds since the first frame, the sum of every `delta`
+    pub total: f64,
+    /// Fixed timesteps due this frame, run this many simulation steps of [`Self::timestep`] seconds
+    pub steps: u32,
+    pub timestep: f32,
+    /// How far the clock is into the next fixed timestep from 0 to 1, e.g. to interpolate between steps
+    pub alpha: f32,
+    pub paused: bool,
+}
+
+/// Owned by the runner and ticked once per frame, see [`crate::UiState::clock_mut`]
+/// In a window frames take as long as they take, headless and while recording every frame is one timestep
+#[derive(Clone, Debug)]
+pub struct Clock {
+    timestep: f32,
+    speed: f32,
+    paused: bool,
+    pending_steps: u32,
+    accumulator: f32,
+    last_tick: Option<Instant>,
+    frames: u64,
+    current: FrameTime,
+}
+
+impl Clock {
+    pub(crate) fn new(timestep: f32, paused: bool) -> Self {
+        check_timestep(timestep);
+        Self {
+            timestep,
+            speed: 1.0,
+            paused,
+            pending_steps: 0,
+            accumulator: 0.0,
+            last_tick: None,
+            frames: 0,
+            current: FrameTime {
+                timestep,
+                paused,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The time of the current frame
+    pub fn frame_time(&self) -> FrameTime {
+        self.current
+    }
+
+    /// Frames ticked so far, the index of the next frame
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    pub(crate) fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Pause and advance by a single timestep on the next frame
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.pending_steps += 1;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Scale simulated time, 2 runs twice as many steps per second
+    /// Negative, infinite and NaN speeds stop the clock
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = if speed.is_finite() {
+            speed.max(0.0)
+        } else {
+            0.0
+        };
+    }
+
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    /// Seconds per fixed step, see [`crate::RunnerConfig::timestep`]
+    /// Panics unless `timestep` is positive and finite
+    pub fn set_timestep(&mut self, timestep: f32) {
+        check_timestep(timestep);
+        self.timestep = timestep;
+    }
+
+    /// Wall clock time since the last call
+    /// Also measure it for fixed ticks, so switching back after a recording doesn't jump ahead
+    pub(crate) fn wall_delta(&mut self) -> f32 {
+        let now = Instant::now();
+        let delta = self
+            .last_tick
+            .map_or(0.0, |last| (now - last).as_secs_f32().min(MAX_DELTA));
+        self.last_tick = Some(now);
+        delta
+    }
+
+    /// Advance by `delta` real seconds
+    pub(crate) fn tick(&mut self, delta: f32) -> FrameTime {
+        let (delta, steps) = if self.paused {
+            let steps = std::mem::take(&mut self.pending_steps);
+            (steps as f32 * self.timestep, steps)
+        } else {
+            self.accumulator += delta * self.speed;
+            let due = (self.accumulator / self.timestep) as u32;
+            self.accumulator -= due as f32 * self.timestep;
+            if due > MAX_STEPS_PER_FRAME {
+                // Subtracting that many steps loses precision
+                self.accumulator = self.accumulator.clamp(0.0, self.timestep);
+            }
+            (delta * self.speed, due.min(MAX_STEPS_PER_FRAME))
+        };
+        self.current = FrameTime {
+            frame: self.frames,
+            delta,
+            total: self.current.total + delta as f64,
+            steps,
+            timestep: self.timestep,
+            alpha: self.accumulator / self.timestep,
+            paused: self.paused,
+        };
+        self.frames += 1;
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn accumulator_splits_into_steps() {
+        let mut clock = Clock::new(0.25, false);
+        let time = clock.tick(0.625);
+        assert_eq!(time.steps, 2);
+        assert_close(time.alpha, 0.5);
+        let time = clock.tick(0.125);
+        assert_eq!(time.steps, 1);
+        assert_close(time.alpha, 0.0);
+        let time = clock.tick(0.0625);
+        assert_eq!(time.steps, 0);
+        assert_close(time.alpha, 0.25);
+        assert_eq!(time.frame, 2);
+        assert_close(time.total as f32, 0.8125);
+    }
+
+    #[test]
+    fn paused_clock_only_runs_requested_steps() {
+        let mut clock = Clock::new(0.25, true);
+        let time = clock.tick(0.5);
+        assert_eq!((time.steps, time.delta), (0, 0.0));
+        assert!(time.paused);
+        clock.step();
+        clock.step();
+        let time = clock.tick(0.5);
+        assert_eq!(time.steps, 2);
+        assert_close(time.delta, 0.5);
+        assert_eq!(clock.tick(0.5).steps, 0);
+        clock.set_paused(false);
+        assert_eq!(clock.tick(0.25).steps, 1);
+    }
+
+    #[test]
+    fn speed_scales_simulated_time() {
+        let mut clock = Clock::new(0.25, false);
+        clock.set_speed(2.0);
+        let time = clock.tick(0.25);
+        assert_eq!(time.steps, 2);
+        assert_close(time.delta, 0.5);
+        clock.set_speed(-1.0);
+        assert_eq!(clock.speed(), 0.0);
+        assert_eq!(clock.tick(0.25).steps, 0);
+    }
+
+    #[test]
+    fn steps_per_frame_are_capped() {
+        let mut clock = Clock::new(0.1, false);
+        clock.set_speed(1e9);
+        assert_eq!(clock.tick(0.25).steps, MAX_STEPS_PER_FRAME);
+        clock.set_speed(1.0);
+        assert_eq!(clock.tick(0.0).steps, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive number of seconds")]
+    fn zero_timestep_is_rejected() {
+        Clock::new(0.1, false).set_timestep(0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "positive number of seconds")]
+    fn nan_timestep_is_rejected() {
+        Clock::new(f32::NAN, false);
+    }
+}
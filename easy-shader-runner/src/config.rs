@@ -0,0 +1,148 @@
+use crate::clock::DEFAULT_TIMESTEP;
+
+type AdapterFilter = Box<dyn Fn(&wgpu::AdapterInfo) -> bool>;
+
+/// Window, surface and device options for the `run_*` functions
+/// ```no_run
+/// # use easy_shader_runner::{wgpu, RunnerConfig};
+/// let config = RunnerConfig::default()
+///     .title("My Tool")
+///     .size(1280, 720)
+///     .present_mode(wgpu::PresentMode::Mailbox);
+/// ```
+pub struct RunnerConfig {
+    pub(crate) title: String,
+    pub(crate) size: Option<glam::UVec2>,
+    pub(crate) fullscreen: bool,
+    pub(crate) present_mode: wgpu::PresentMode,
+    pub(crate) power_preference: wgpu::PowerPreference,
+    pub(crate) backends: Option<wgpu::Backends>,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub(crate) adapter: Option<AdapterFilter>,
+    pub(crate) clear_color: wgpu::Color,
+    pub(crate) features: wgpu::Features,
+    pub(crate) limits: wgpu::Limits,
+    pub(crate) timestep: f32,
+    pub(crate) paused: bool,
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    pub(crate) record_input: Option<std::path::PathBuf>,
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    pub(crate) replay_input: Option<std::path::PathBuf>,
+}
+
+impl Default for RunnerConfig {
+    fn default() -> Self {
+        Self {
+            title: "runner".to_owned(),
+            size: None,
+            fullscreen: false,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            backends: None,
+            adapter: None,
+            clear_color: wgpu::Color::GREEN,
+            features: wgpu::Features::empty(),
+            limits: Default::default(),
+            timestep: DEFAULT_TIMESTEP,
+            paused: false,
+            #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+            record_input: None,
+            #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+            replay_input: None,
+        }
+    }
+}
+
+impl RunnerConfig {
+    /// Window title, also used as the Wayland app id
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Initial inner size of the window in logical pixels
+    /// On the web the canvas fills the browser window instead
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some(glam::uvec2(width, height));
+        self
+    }
+
+    /// Start in borderless fullscreen on the current monitor
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    /// Initial present mode, [`crate::UiState::vsync`] switches between vsync and no vsync later on
+    pub fn present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    pub fn power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Backends to choose an adapter from, by default `WGPU_BACKEND` or the primary backends
+    pub fn backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = Some(backends);
+        self
+    }
+
+    /// Use the first adapter `choose` returns true for, instead of going by [`Self::power_preference`]
+    /// Falls back to the power preference if no adapter is chosen, and is ignored on the web
+    pub fn adapter(mut self, choose: impl Fn(&wgpu::AdapterInfo) -> bool + 'static) -> Self {
+        self.adapter = Some(Box::new(choose));
+        self
+    }
+
+    /// Fills the shader viewport before the final shader pass
+    pub fn clear_color(mut self, clear_color: wgpu::Color) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// Device features on top of the ones the runner needs
+    pub fn features(mut self, features: wgpu::Features) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Device limits, the push constant size is raised to what the runner needs
+    pub fn limits(mut self, limits: wgpu::Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Seconds per fixed step of [`crate::FrameTime::steps`], 1/60 by default
+    /// Headless renders advance by one timestep per frame
+    /// Panics unless `seconds` is positive and finite
+    pub fn timestep(mut self, seconds: f32) -> Self {
+        crate::clock::check_timestep(seconds);
+        self.timestep = seconds;
+        self
+    }
+
+    /// Start with the [`crate::Clock`] paused
+    pub fn paused(mut self, paused: bool) -> Self {
+        self.paused = paused;
+        self
+    }
+
+    /// Write keyboard, mouse and resize input that reaches the controller to `path` as JSON lines,
+    /// along with the length of every frame so a replay ticks the [`crate::Clock`] the same way
+    /// Input taken by egui, pastes and dropped files aren't recorded
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    pub fn record_input(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.record_input = Some(path.into());
+        self
+    }
+
+    /// Feed input from [`Self::record_input`] to the controller instead of live input until the file ends
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    pub fn replay_input(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.replay_input = Some(path.into());
+        self
+    }
+}
@@ -1,21 +1,21 @@
+use crate::{config::RunnerConfig, push_constants::MAX_PUSH_CONSTANT_SIZE};
 use egui_winit::winit::{dpi::PhysicalSize, window::Window};
 use std::sync::Arc;
 
 pub struct GraphicsContext {
-    pub surface: wgpu::Surface<'static>,
+    surface: Option<wgpu::Surface<'static>>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
 }
 
 impl GraphicsContext {
-    pub async fn new(window: Arc<Window>, initial_size: PhysicalSize<u32>) -> GraphicsContext {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::PRIMARY),
-            flags: wgpu::InstanceFlags::default().with_env(),
-            dx12_shader_compiler: wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default(),
-            gles_minor_version: wgpu::Gles3MinorVersion::Automatic,
-        });
+    pub async fn new(
+        window: Arc<Window>,
+        initial_size: PhysicalSize<u32>,
+        config: &RunnerConfig,
+    ) -> GraphicsContext {
+        let instance = create_instance(config);
 
         #[cfg(target_arch = "wasm32")]
         let canvas = {
@@ -39,45 +39,18 @@ impl GraphicsContext {
         }
         let initial_surface = initial_surface.expect("Failed to create surface from window");
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&initial_surface),
-                force_fallback_adapter: false,
-            })
+        let adapter = request_adapter(&instance, Some(&initial_surface), config)
             .await
-            .unwrap();
-
-        let (features, limits) = if cfg!(feature = "emulate_constants") {
-            Default::default()
-        } else {
-            (
-                wgpu::Features::PUSH_CONSTANTS,
-                wgpu::Limits {
-                    max_push_constant_size: 128,
-                    ..Default::default()
-                },
-            )
-        };
+            .expect("Failed to find an appropriate adapter");
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: features,
-                    required_limits: limits,
-                    memory_hints: Default::default(),
-                },
-                None,
-            )
-            .await
-            .expect("Failed to create device");
+        let (device, queue) = request_device(&adapter, config).await;
 
         fn auto_configure_surface<'a>(
             adapter: &wgpu::Adapter,
             device: &wgpu::Device,
             surface: wgpu::Surface<'a>,
             size: PhysicalSize<u32>,
+            present_mode: wgpu::PresentMode,
         ) -> (wgpu::Surface<'a>, wgpu::SurfaceConfiguration) {
             let capabilities = surface.get_capabilities(adapter);
             let mut surface_config = surface
@@ -88,34 +61,164 @@ impl GraphicsContext {
                         capabilities
                     )
                 });
-            surface_config.present_mode = wgpu::PresentMode::AutoVsync;
+            surface_config.present_mode = if capabilities.present_modes.contains(&present_mode) {
+                present_mode
+            } else {
+                log::warn!("Present mode {present_mode:?} is unsupported, using vsync");
+                wgpu::PresentMode::AutoVsync
+            };
+            // Allows reading back frames for screenshots
+            if capabilities.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+                surface_config.usage |= wgpu::TextureUsages::COPY_SRC;
+            }
             surface_config.format =
                 egui_wgpu::preferred_framebuffer_format(&capabilities.formats).unwrap();
             surface.configure(device, &surface_config);
             (surface, surface_config)
         }
 
-        let (surface, config) =
-            auto_configure_surface(&adapter, &device, initial_surface, initial_size);
+        let (surface, config) = auto_configure_surface(
+            &adapter,
+            &device,
+            initial_surface,
+            initial_size,
+            config.present_mode,
+        );
 
         GraphicsContext {
-            surface,
+            surface: Some(surface),
             device,
             queue,
             config,
         }
     }
 
+    /// Create a context without a window or surface
+    /// `config` describes the offscreen render target instead
+    /// Window and surface options of `runner_config` are ignored
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn set_vsync(&mut self, enable: bool) {
-        let present_mode = if enable {
-            wgpu::PresentMode::AutoVsync
-        } else {
-            wgpu::PresentMode::AutoNoVsync
+    pub async fn new_headless(size: glam::UVec2, runner_config: &RunnerConfig) -> GraphicsContext {
+        let instance = create_instance(runner_config);
+
+        let adapter = request_adapter(&instance, None, runner_config)
+            .await
+            .expect("Failed to find an appropriate adapter");
+
+        let (device, queue) = request_device(&adapter, runner_config).await;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: size.x,
+            height: size.y,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
         };
-        if self.config.present_mode != present_mode {
-            self.config.present_mode = present_mode;
-            self.surface.configure(&self.device, &self.config);
+
+        GraphicsContext {
+            surface: None,
+            device,
+            queue,
+            config,
+        }
+    }
+
+    /// `None` when rendering offscreen, see [`Self::new_headless`]
+    pub fn surface(&self) -> Option<&wgpu::Surface<'static>> {
+        self.surface.as_ref()
+    }
+
+    pub fn configure_surface(&self) {
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_vsync(&mut self, enable: bool) {
+        if self.vsync() != enable {
+            self.config.present_mode = if enable {
+                wgpu::PresentMode::AutoVsync
+            } else {
+                wgpu::PresentMode::AutoNoVsync
+            };
+            self.configure_surface();
+        }
+    }
+
+    /// Whether the present mode waits for vertical blanks
+    pub fn vsync(&self) -> bool {
+        matches!(
+            self.config.present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed
+        )
+    }
+}
+
+fn create_instance(config: &RunnerConfig) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: config
+            .backends
+            .or_else(wgpu::util::backend_bits_from_env)
+            .unwrap_or(wgpu::Backends::PRIMARY),
+        flags: wgpu::InstanceFlags::default().with_env(),
+        dx12_shader_compiler: wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default(),
+        gles_minor_version: wgpu::Gles3MinorVersion::Automatic,
+    })
+}
+
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface<'_>>,
+    config: &RunnerConfig,
+) -> Option<wgpu::Adapter> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(choose) = &config.adapter {
+        let chosen = instance
+            .enumerate_adapters(wgpu::Backends::all())
+            .into_iter()
+            .filter(|adapter| surface.is_none_or(|surface| adapter.is_surface_supported(surface)))
+            .find(|adapter| choose(&adapter.get_info()));
+        match chosen {
+            Some(adapter) => return Some(adapter),
+            None => log::warn!("No adapter was chosen, going by power preference"),
         }
     }
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: config.power_preference,
+            compatible_surface: surface,
+            force_fallback_adapter: false,
+        })
+        .await
+}
+
+async fn request_device(
+    adapter: &wgpu::Adapter,
+    config: &RunnerConfig,
+) -> (wgpu::Device, wgpu::Queue) {
+    let mut features = config.features;
+    let mut limits = config.limits.clone();
+    if !cfg!(feature = "emulate_constants") {
+        features |= wgpu::Features::PUSH_CONSTANTS;
+        limits.max_push_constant_size = limits.max_push_constant_size.max(MAX_PUSH_CONSTANT_SIZE);
+    }
+
+    // Pass timings fall back to the CPU without it
+    let features = features | (adapter.features() & wgpu::Features::TIMESTAMP_QUERY);
+
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: features,
+                required_limits: limits,
+                memory_hints: Default::default(),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create device")
 }
@@ -1,8 +1,27 @@
-use crate::bind_group_buffer::BufferDescriptor;
-use egui_winit::winit::event::{ElementState, KeyEvent, MouseButton};
+use crate::{
+    bind_group_buffer::BufferDescriptor,
+    clock::FrameTime,
+    key_bindings::{KeyBindings, KeyInput},
+    layout::LayoutSource,
+    passes::PassDescriptor,
+    transfer::{DroppedFile, Paste},
+};
+use egui_winit::winit::{
+    event::{ElementState, MouseButton, TouchPhase},
+    keyboard::ModifiersState,
+};
 use glam::*;
+use std::{path::PathBuf, sync::Arc};
 
 pub trait ControllerTrait: 'static {
+    /// Pushed to the fragment shader every frame, see [`Self::prepare_render`]
+    /// Must be at most 128 bytes and a multiple of 4 bytes, which is checked at compile time
+    type PushConstants: bytemuck::NoUninit;
+
+    /// Pushed to the compute shader on every dispatch from [`Self::update`], with the same limits
+    #[cfg(feature = "compute")]
+    type ComputePushConstants: bytemuck::NoUninit;
+
     fn resize(&mut self, _size: UVec2);
 
     fn mouse_move(&mut self, _position: DVec2) {}
@@ -11,35 +30,120 @@ pub trait ControllerTrait: 'static {
 
     fn mouse_input(&mut self, _state: ElementState, _button: MouseButton) {}
 
-    fn keyboard_input(&mut self, _key: KeyEvent) {}
+    fn cursor_entered(&mut self) {}
+
+    /// Buttons released outside the window aren't reported, so treat them as released here
+    fn cursor_left(&mut self) {}
+
+    /// Called when the pointer moves between the shader viewport and egui, or leaves the window
+    /// Mouse events are only withheld while egui is using the pointer, so check this before e.g. painting
+    fn pointer_over_viewport(&mut self, _over: bool) {}
+
+    /// A finger on a touch screen, `id` stays the same from [`TouchPhase::Started`] until it ends or is cancelled
+    /// The position is relative to the shader viewport like in [`Self::mouse_move`]
+    fn touch(&mut self, _id: u64, _phase: TouchPhase, _position: DVec2) {}
+
+    /// Trackpad pinch, a positive delta means zooming in
+    /// Only macOS and iOS report gestures, elsewhere pinches arrive as two [`Self::touch`]es
+    fn pinch_gesture(&mut self, _delta: f64, _phase: TouchPhase) {}
+
+    /// Trackpad pan with two fingers in physical pixels, only reported on iOS
+    fn pan_gesture(&mut self, _delta: Vec2, _phase: TouchPhase) {}
+
+    /// Trackpad rotation in degrees, counterclockwise is positive
+    fn rotation_gesture(&mut self, _delta: f32, _phase: TouchPhase) {}
+
+    /// Receives every key that isn't bound in [`Self::key_bindings`]
+    fn keyboard_input(&mut self, _key: KeyInput) {}
+
+    /// Shift, Control, Alt and Super, e.g. to tell a click from a Shift-click in [`Self::mouse_input`]
+    fn modifiers_changed(&mut self, _modifiers: ModifiersState) {}
 
-    fn prepare_render(&mut self, offset: Vec2) -> impl bytemuck::NoUninit;
+    /// Keys and buttons released while unfocused aren't reported
+    fn focused(&mut self, _focused: bool) {}
+
+    /// A file dropped onto the window, read it with [`DroppedFile::read`]
+    fn file_dropped(&mut self, _file: DroppedFile) {}
+
+    /// A file dragged over the window, `None` once it leaves without being dropped
+    /// Only reported natively, the browser doesn't tell which file is dragged
+    fn file_hovered(&mut self, _path: Option<PathBuf>) {}
+
+    /// Ctrl+V (Cmd+V on macOS) while egui doesn't have keyboard focus
+    /// Natively the key only reaches [`Self::keyboard_input`] if the clipboard is empty
+    fn paste(&mut self, _paste: Paste) {}
+
+    /// Keys the runner handles itself, queried once when the window is created
+    fn key_bindings(&self) -> KeyBindings {
+        KeyBindings::default()
+    }
+
+    /// Called before the shader passes of every frame
+    /// `time` is the same for the following [`Self::update`]
+    fn prepare_render(&mut self, offset: Vec2, time: FrameTime) -> Self::PushConstants;
 
     /// Run the compute shader after rendering
+    /// Dispatch once per [`FrameTime::steps`] for a simulation that doesn't depend on the frame rate
     #[cfg(feature = "compute")]
     fn update<
         F: Fn(
-            UVec3, // dimensions
-            UVec3, // threads (same as declared in compute shader)
-            &[u8], // push_constants
+            ComputeEntry, // which of the described entry points to dispatch
+            UVec3,        // dimensions
+            UVec3,        // threads (same as declared in compute shader)
+            &Self::ComputePushConstants,
         ),
     >(
         &mut self,
         _compute: F,
         _allowed_duration: f32,
+        _time: FrameTime,
     ) {
     }
 
-    /// Describe the SSBO's you want to use
+    /// Describe the compute entry points [`Self::update`] can dispatch
+    /// The index of an entry point is its [`ComputeEntry::Handle`]
+    #[cfg(feature = "compute")]
+    fn describe_compute_entry_points(&self) -> Vec<&'static str> {
+        vec!["main_cs"]
+    }
+
+    /// Choose the vertex and fragment entry points of the final pass
+    /// Passes from [`Self::describe_passes`] use the same vertex entry point
+    fn describe_entry_points(&self) -> EntryPoints {
+        EntryPoints::default()
+    }
+
+    /// Describe the SSBO's, uniform buffers, textures and samplers you want to use
     /// Outer index signifies the descriptor set
     /// Inner index signifies the binding
+    /// Described again after a hot reload, resources are only recreated where the description changed
     fn describe_buffers(&self) -> Vec<Vec<BufferDescriptor>> {
         vec![]
     }
 
+    /// Whether [`Self::describe_buffers`] is taken as is or patched with how the shader module uses each binding
+    /// Either way, bindings the shader disagrees with or uses without a description are reported before any pipeline is created
+    fn layout_source(&self) -> LayoutSource {
+        LayoutSource::Described
+    }
+
+    /// Describe fragment passes that run in order before `main_fs`, each rendering into its own texture
+    /// The descriptor set after the ones from [`Self::describe_buffers`] holds a sampler at binding 0
+    /// and the output of pass `i` at binding `i + 1`
+    /// The sampler filters linearly, or not at all if a [`PassDescriptor::format`] can't be filtered
+    /// Outputs of earlier passes are from the current frame, the rest are from the previous frame
+    /// Pass textures are the size of the shader viewport and are drawn without an offset
+    fn describe_passes(&self) -> Vec<PassDescriptor> {
+        vec![]
+    }
+
     /// Receive the buffers described in [`Self::describe_buffers`] with [`BufferDescriptor::cpu_writable`] set to true
     /// The order is the same as described
-    fn receive_buffers(&mut self, _buffers: Vec<wgpu::Buffer>) {}
+    /// Called again after a hot reload, buffers whose description didn't change are the same handles
+    fn receive_buffers(&mut self, _buffers: Vec<Arc<wgpu::Buffer>>) {}
+
+    /// Same as [`Self::receive_buffers`] but for textures and storage textures
+    fn receive_textures(&mut self, _textures: Vec<Arc<wgpu::Texture>>) {}
 
     fn ui(
         &mut self,
@@ -49,3 +153,58 @@ pub trait ControllerTrait: 'static {
     ) {
     }
 }
+
+/// Entry point names in the shader module, see [`ControllerTrait::describe_entry_points`]
+#[derive(Clone, Debug)]
+pub struct EntryPoints {
+    pub vertex: &'static str,
+    pub fragment: &'static str,
+}
+
+impl Default for EntryPoints {
+    fn default() -> Self {
+        Self {
+            vertex: "main_vs",
+            fragment: "main_fs",
+        }
+    }
+}
+
+/// Selects one of [`ControllerTrait::describe_compute_entry_points`]
+#[cfg(feature = "compute")]
+#[derive(Clone, Copy, Debug)]
+pub enum ComputeEntry<'a> {
+    Handle(usize),
+    Name(&'a str),
+}
+
+#[cfg(feature = "compute")]
+impl ComputeEntry<'_> {
+    /// Index into `entry_points`, panics with the described entry points if there's no such entry point
+    pub(crate) fn index(self, entry_points: &[&str]) -> usize {
+        match self {
+            Self::Handle(index) if index < entry_points.len() => Some(index),
+            Self::Handle(_) => None,
+            Self::Name(name) => entry_points.iter().position(|&entry| entry == name),
+        }
+        .unwrap_or_else(|| {
+            panic!(
+                "Unknown compute entry point {self:?}, described entry points are {entry_points:?}"
+            )
+        })
+    }
+}
+
+#[cfg(feature = "compute")]
+impl From<usize> for ComputeEntry<'_> {
+    fn from(handle: usize) -> Self {
+        Self::Handle(handle)
+    }
+}
+
+#[cfg(feature = "compute")]
+impl<'a> From<&'a str> for ComputeEntry<'a> {
+    fn from(name: &'a str) -> Self {
+        Self::Name(name)
+    }
+}
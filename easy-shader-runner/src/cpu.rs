@@ -0,0 +1,432 @@
+use crate::{
+    bind_group_buffer::BindingKind, capture::Image, clock::Clock, config::RunnerConfig,
+    controller::ControllerTrait,
+};
+use bytemuck::{CheckedBitPattern, NoUninit};
+use glam::*;
+use std::{
+    any::TypeId,
+    sync::{Arc, OnceLock},
+};
+
+/// Entry points of a shader crate compiled for the host, run by [`CpuRunner`]
+/// Forward to the `#[spirv]` functions, taking their buffers from [`CpuBindings`]
+/// ```ignore
+/// impl CpuShader<Controller> for Shader {
+///     fn fragment(&self, _entry: &str, frag_coord: Vec4, constants: &FragmentConstants, bindings: &mut CpuBindings) -> Vec4 {
+///         let mut output = Vec4::ZERO;
+///         shader::main_fs(frag_coord, constants, bindings.storage_mut(0, 0), &mut output);
+///         output
+///     }
+/// }
+/// ```
+pub trait CpuShader<C: ControllerTrait>: Sync {
+    /// Shade the pixel at `frag_coord`, which is the pixel center like `#[spirv(frag_coord)]`
+    /// `entry` is the fragment entry point from [`ControllerTrait::describe_entry_points`]
+    fn fragment(
+        &self,
+        entry: &str,
+        frag_coord: Vec4,
+        constants: &C::PushConstants,
+        bindings: &mut CpuBindings,
+    ) -> Vec4;
+
+    /// Run a single invocation of the compute entry point `entry`
+    /// Workgroup shared memory and barriers can't be emulated, since invocations of a workgroup don't run in lockstep
+    #[cfg(feature = "compute")]
+    fn compute(
+        &self,
+        entry: &str,
+        global_invocation_id: UVec3,
+        constants: &C::ComputePushConstants,
+        bindings: &mut CpuBindings,
+    );
+}
+
+/// A controller whose push constants can be shared between the threads of a [`CpuRunner`], implemented for all of them
+#[cfg(feature = "compute")]
+pub trait CpuController: ControllerTrait<PushConstants: Sync, ComputePushConstants: Sync> {}
+
+#[cfg(feature = "compute")]
+impl<C: ControllerTrait<PushConstants: Sync, ComputePushConstants: Sync>> CpuController for C {}
+
+/// A controller whose push constants can be shared between the threads of a [`CpuRunner`], implemented for all of them
+#[cfg(not(feature = "compute"))]
+pub trait CpuController: ControllerTrait<PushConstants: Sync> {}
+
+#[cfg(not(feature = "compute"))]
+impl<C: ControllerTrait<PushConstants: Sync>> CpuController for C {}
+
+/// Host memory for the buffers of [`ControllerTrait::describe_buffers`], indexed the same way
+/// Textures and samplers are kept as placeholders so buffer indices still line up
+#[derive(Clone, Debug, Default)]
+pub struct CpuBindings {
+    sets: Vec<Vec<Option<CpuBuffer>>>,
+}
+
+/// The largest alignment of the vector and matrix types a shader can read
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C, align(16))]
+struct Chunk([u8; 16]);
+
+// SAFETY: a byte array without padding, aligned to its own size
+unsafe impl bytemuck::Zeroable for Chunk {}
+unsafe impl bytemuck::Pod for Chunk {}
+
+#[derive(Clone, Debug)]
+struct CpuBuffer {
+    /// Shared with the copies of a parallel run until they write to it
+    chunks: Arc<Vec<Chunk>>,
+    /// The described size in bytes
+    len: usize,
+    /// The type the contents were checked against, so invocations don't check the whole buffer again
+    checked: OnceLock<TypeId>,
+}
+
+impl CpuBuffer {
+    /// `None` for textures and samplers
+    fn new(descriptor: &crate::BufferDescriptor) -> Option<Self> {
+        match descriptor.kind {
+            BindingKind::StorageBuffer | BindingKind::UniformBuffer => {
+                let len = descriptor.data.len();
+                let mut chunks = vec![Chunk::default(); len.div_ceil(size_of::<Chunk>())];
+                bytemuck::cast_slice_mut::<_, u8>(&mut chunks)[..len]
+                    .copy_from_slice(descriptor.data);
+                Some(Self {
+                    chunks: Arc::new(chunks),
+                    len,
+                    checked: OnceLock::new(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &bytemuck::cast_slice(&self.chunks)[..self.len]
+    }
+
+    /// Panics if any element isn't a valid `T`, returns the number of elements
+    fn check<T: CheckedBitPattern + 'static>(&self) -> usize {
+        let size = size_of::<T>();
+        assert!(size != 0, "Zero sized types can't be read from a buffer");
+        assert!(
+            align_of::<T>() <= align_of::<Chunk>(),
+            "Buffers are only aligned to {} bytes",
+            align_of::<Chunk>()
+        );
+        let len = self.len / size;
+        if self.checked.get() != Some(&TypeId::of::<T>()) {
+            bytemuck::checked::cast_slice::<_, T>(&self.bytes()[..len * size]);
+            let _ = self.checked.set(TypeId::of::<T>());
+        }
+        len
+    }
+}
+
+impl CpuBindings {
+    fn new(sets: &[Vec<crate::BufferDescriptor>]) -> Self {
+        let sets = sets
+            .iter()
+            .map(|set| set.iter().map(CpuBuffer::new).collect())
+            .collect();
+        Self { sets }
+    }
+
+    fn buffer(&self, set: usize, binding: usize) -> &CpuBuffer {
+        self.sets
+            .get(set)
+            .and_then(|bindings| bindings.get(binding))
+            .and_then(Option::as_ref)
+            .unwrap_or_else(|| panic!("No buffer bound at set {set}, binding {binding}"))
+    }
+
+    fn buffer_slot(&mut self, set: usize, binding: usize) -> &mut Option<CpuBuffer> {
+        self.sets
+            .get_mut(set)
+            .and_then(|bindings| bindings.get_mut(binding))
+            .unwrap_or_else(|| panic!("Nothing bound at set {set}, binding {binding}"))
+    }
+
+    fn buffer_mut(&mut self, set: usize, binding: usize) -> &mut CpuBuffer {
+        self.buffer_slot(set, binding)
+            .as_mut()
+            .unwrap_or_else(|| panic!("No buffer bound at set {set}, binding {binding}"))
+    }
+
+    /// Contents of a storage buffer, panics if any element isn't a valid `T`
+    pub fn storage<T: CheckedBitPattern + 'static>(&self, set: usize, binding: usize) -> &[T] {
+        let buffer = self.buffer(set, binding);
+        let len = buffer.check::<T>();
+        // SAFETY: the chunks are aligned for `T` and hold `len` valid `T`s, since they were checked and are
+        // only written through `&mut [T]` of the checked type, `bytes_mut` or a parallel run, which reset the check
+        unsafe { std::slice::from_raw_parts(buffer.chunks.as_ptr().cast(), len) }
+    }
+
+    /// Same as [`Self::storage`] for a buffer the shader writes to
+    pub fn storage_mut<T: CheckedBitPattern + NoUninit + 'static>(
+        &mut self,
+        set: usize,
+        binding: usize,
+    ) -> &mut [T] {
+        let buffer = self.buffer_mut(set, binding);
+        if buffer.checked.get() != Some(&TypeId::of::<T>()) {
+            buffer.checked = OnceLock::new();
+        }
+        let len = buffer.check::<T>();
+        let chunks = Arc::make_mut(&mut buffer.chunks);
+        // SAFETY: same as in `storage`, and `T` has no padding so whatever the shader writes stays initialized
+        unsafe { std::slice::from_raw_parts_mut(chunks.as_mut_ptr().cast(), len) }
+    }
+
+    /// A uniform buffer holding a single `T`
+    pub fn uniform<T: CheckedBitPattern>(&self, set: usize, binding: usize) -> &T {
+        let bytes = self.bytes(set, binding);
+        bytemuck::checked::from_bytes(&bytes[..size_of::<T>()])
+    }
+
+    /// Raw contents of a buffer
+    pub fn bytes(&self, set: usize, binding: usize) -> &[u8] {
+        self.buffer(set, binding).bytes()
+    }
+
+    /// Same as [`Self::bytes`], e.g. to write a buffer the way the GPU controller writes it with a queue
+    pub fn bytes_mut(&mut self, set: usize, binding: usize) -> &mut [u8] {
+        let buffer = self.buffer_mut(set, binding);
+        buffer.checked = OnceLock::new();
+        &mut bytemuck::cast_slice_mut(Arc::make_mut(&mut buffer.chunks).as_mut_slice())
+            [..buffer.len]
+    }
+
+    /// Run `f` for every item of `work` on its own thread and copy of the bindings, then apply what the copies wrote
+    /// A copy only sees its own writes, conflicting writes to the same byte are applied in an unspecified order
+    fn run_parallel<W: Send>(&mut self, work: Vec<W>, f: impl Fn(W, &mut CpuBindings) + Sync) {
+        let copies = std::thread::scope(|scope| {
+            let threads = work
+                .into_iter()
+                .map(|work| {
+                    let (mut bindings, f) = (self.clone(), &f);
+                    scope.spawn(move || {
+                        f(work, &mut bindings);
+                        bindings
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| {
+                    thread
+                        .join()
+                        .unwrap_or_else(|err| std::panic::resume_unwind(err))
+                })
+                .collect::<Vec<_>>()
+        });
+        // Copies that weren't written to still share their chunks, which are dropped here
+        let written = copies
+            .into_iter()
+            .flat_map(|copy| {
+                copy.sets
+                    .into_iter()
+                    .enumerate()
+                    .flat_map(|(set, bindings)| {
+                        bindings
+                            .into_iter()
+                            .enumerate()
+                            .filter_map(move |(binding, buffer)| {
+                                Some((set, binding, buffer?.chunks))
+                            })
+                    })
+            })
+            .filter(|(set, binding, chunks)| {
+                !Arc::ptr_eq(chunks, &self.buffer(*set, *binding).chunks)
+            })
+            .collect::<Vec<_>>();
+        let before = self.clone();
+        for (set, binding, chunks) in written {
+            let previous = &before.buffer(set, binding).chunks;
+            let buffer = self.buffer_mut(set, binding);
+            buffer.checked = OnceLock::new();
+            let target = Arc::make_mut(&mut buffer.chunks);
+            for ((target, previous), written) in
+                target.iter_mut().zip(previous.iter()).zip(chunks.iter())
+            {
+                if previous == written {
+                    continue;
+                }
+                for ((target, previous), written) in
+                    target.0.iter_mut().zip(previous.0).zip(written.0)
+                {
+                    if previous != written {
+                        *target = written;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Drives a controller like [`crate::HeadlessRunner`] but runs the shader on the CPU, without a GPU
+/// Invocations run in parallel, each thread on a copy of the bindings that only sees its own writes until the draw or dispatch is done,
+/// so like on a GPU, invocations can't rely on each other's writes without barriers
+/// [`ControllerTrait::receive_buffers`] is never called, read and write buffers with [`Self::bindings_mut`] instead
+/// Passes from [`ControllerTrait::describe_passes`] aren't run
+pub struct CpuRunner<C: CpuController, S: CpuShader<C>> {
+    controller: C,
+    shader: S,
+    bindings: CpuBindings,
+    size: UVec2,
+    clock: Clock,
+}
+
+impl<C: CpuController, S: CpuShader<C>> CpuRunner<C, S> {
+    /// Only the clock options of `config` are used
+    pub fn new(mut controller: C, shader: S, size: UVec2, config: RunnerConfig) -> Self {
+        if !controller.describe_passes().is_empty() {
+            log::warn!("Passes aren't run on the CPU, only the final fragment entry point");
+        }
+        let bindings = CpuBindings::new(&controller.describe_buffers());
+        controller.resize(size);
+        Self {
+            controller,
+            shader,
+            bindings,
+            size,
+            clock: Clock::new(config.timestep, config.paused),
+        }
+    }
+
+    /// Render a single frame, pixels are encoded as sRGB the same as [`crate::HeadlessRunner::render_frame`]
+    /// Compute runs after rendering, the same as in a window
+    pub fn render_frame(&mut self) -> Image {
+        let time = self.clock.tick(self.clock.timestep());
+        let constants = self.controller.prepare_render(Vec2::ZERO, time);
+        let entry = self.controller.describe_entry_points().fragment;
+        let (shader, width) = (&self.shader, self.size.x as usize);
+        let mut data = vec![0; width * self.size.y as usize * 4];
+        let rows = (self.size.y as usize).div_ceil(threads()).max(1);
+        let bands = data.chunks_mut(rows * width * 4).enumerate().collect();
+        self.bindings.run_parallel(bands, |(band, data), bindings| {
+            for (i, pixel) in data.chunks_exact_mut(4).enumerate() {
+                let (x, y) = (i % width, band * rows + i / width);
+                let frag_coord = vec4(x as f32 + 0.5, y as f32 + 0.5, 0.0, 1.0);
+                let color = shader.fragment(entry, frag_coord, &constants, bindings);
+                pixel.copy_from_slice(&to_srgb8(color));
+            }
+        });
+        #[cfg(feature = "compute")]
+        {
+            let entry_points = self.controller.describe_compute_entry_points();
+            let bindings = std::cell::RefCell::new(std::mem::take(&mut self.bindings));
+            self.controller.update(
+                |entry, dimensions, threads, constants| {
+                    let entry = entry_points[entry.index(&entry_points)];
+                    // Whole workgroups run like on the GPU, so shaders still check their bounds
+                    let workgroups = (dimensions.as_vec3() / threads.as_vec3()).ceil().as_uvec3();
+                    let count = workgroups.element_product() as usize;
+                    let per_thread = count.div_ceil(self::threads()).max(1);
+                    let ranges = (0..count)
+                        .step_by(per_thread)
+                        .map(|start| start..count.min(start + per_thread))
+                        .collect();
+                    bindings
+                        .borrow_mut()
+                        .run_parallel(ranges, |range, bindings| {
+                            for workgroup in range {
+                                let workgroup = workgroup as u32;
+                                let workgroup = uvec3(
+                                    workgroup % workgroups.x,
+                                    workgroup / workgroups.x % workgroups.y,
+                                    workgroup / (workgroups.x * workgroups.y),
+                                );
+                                for local in ids(threads) {
+                                    let id = workgroup * threads + local;
+                                    shader.compute(entry, id, constants, bindings);
+                                }
+                            }
+                        });
+                },
+                f32::INFINITY,
+                time,
+            );
+            self.bindings = bindings.into_inner();
+        }
+        Image {
+            size: self.size,
+            data,
+        }
+    }
+
+    pub fn controller(&self) -> &C {
+        &self.controller
+    }
+
+    pub fn controller_mut(&mut self) -> &mut C {
+        &mut self.controller
+    }
+
+    /// Recreate the buffer at `set` and `binding` from [`ControllerTrait::describe_buffers`] right away
+    /// The same as [`crate::UiState::reallocate_buffer`] in a window
+    pub fn reallocate_buffer(&mut self, set: usize, binding: usize) {
+        let descriptors = self.controller.describe_buffers();
+        let descriptor = descriptors
+            .get(set)
+            .and_then(|descriptors| descriptors.get(binding))
+            .unwrap_or_else(|| panic!("Nothing described at set {set}, binding {binding}"));
+        *self.bindings.buffer_slot(set, binding) = CpuBuffer::new(descriptor);
+    }
+
+    pub fn bindings(&self) -> &CpuBindings {
+        &self.bindings
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut CpuBindings {
+        &mut self.bindings
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+}
+
+/// Threads to split draws and dispatches across
+fn threads() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
+
+/// Every id in `0..size`, x varying fastest like a GPU dispatch
+#[cfg(feature = "compute")]
+fn ids(size: UVec3) -> impl Iterator<Item = UVec3> {
+    (0..size.z)
+        .flat_map(move |z| (0..size.y).flat_map(move |y| (0..size.x).map(move |x| uvec3(x, y, z))))
+}
+
+/// Encode like a `Rgba8UnormSrgb` render target
+fn to_srgb8(color: Vec4) -> [u8; 4] {
+    let encode = |c: f32| {
+        let c = c.clamp(0.0, 1.0);
+        if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    };
+    let rgb = color.xyz().to_array().map(encode);
+    [rgb[0], rgb[1], rgb[2], color.w.clamp(0.0, 1.0)].map(|c| (c * 255.0).round() as u8)
+}
+
+/// Render `frames` frames at `size` on the CPU
+pub fn run_cpu<C: CpuController, S: CpuShader<C>>(
+    controller: C,
+    shader: S,
+    size: UVec2,
+    frames: u32,
+    config: RunnerConfig,
+) -> Vec<Image> {
+    crate::setup_logging();
+    let mut runner = CpuRunner::new(controller, shader, size, config);
+    (0..frames).map(|_| runner.render_frame()).collect()
+}
@@ -0,0 +1,196 @@
+use crate::ui::{graph, UiState};
+use std::collections::VecDeque;
+use web_time::Instant;
+
+/// Frames kept for [`UiState::frame_times`]
+pub(crate) const FRAME_HISTORY_LEN: usize = 600;
+
+/// Frame time statistics in milliseconds, see [`UiState::frame_stats`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+    /// Median
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+}
+
+impl FrameStats {
+    /// `None` if there are no frame times
+    pub fn new(frame_times: impl IntoIterator<Item = f32>) -> Option<Self> {
+        let mut sorted = frame_times.into_iter().collect::<Vec<_>>();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(f32::total_cmp);
+        let percentile = |p: f32| {
+            let rank = (p / 100.0 * sorted.len() as f32).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        Some(Self {
+            min: sorted[0],
+            avg: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            max: sorted[sorted.len() - 1],
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+        })
+    }
+}
+
+/// Measures the time between frames
+pub(crate) struct FrameTimer {
+    last: Option<Instant>,
+}
+
+impl FrameTimer {
+    pub fn new() -> Self {
+        Self { last: None }
+    }
+
+    /// Milliseconds since the previous tick, `None` on the first one
+    pub fn tick(&mut self) -> Option<f32> {
+        let now = Instant::now();
+        let elapsed = self.last.map(|last| (now - last).as_secs_f32() * 1000.0);
+        self.last = Some(now);
+        elapsed
+    }
+}
+
+/// Frames per second over the last second of frame times
+/// Computed from their mean, since faster frame rates don't fit in the history
+pub(crate) fn fps(frame_times: &VecDeque<f32>) -> u32 {
+    let (mut total, mut frames) = (0.0, 0);
+    for &frame_time in frame_times.iter().rev() {
+        if total >= 1000.0 {
+            break;
+        }
+        total += frame_time;
+        frames += 1;
+    }
+    if total <= 0.0 {
+        return 0;
+    }
+    (frames as f32 * 1000.0 / total).round() as u32
+}
+
+/// Graphs [`UiState::frame_times`] with the statistics of the last `window` frames
+/// Add it to any [`egui::Ui`] in [`crate::ControllerTrait::ui`]
+pub struct FrameTimeGraph<'a> {
+    ui_state: &'a UiState,
+    window: usize,
+    height: f32,
+}
+
+impl<'a> FrameTimeGraph<'a> {
+    pub fn new(ui_state: &'a UiState) -> Self {
+        Self {
+            ui_state,
+            window: 120,
+            height: 80.0,
+        }
+    }
+
+    /// Number of recent frames the statistics are computed over
+    pub fn window(mut self, frames: usize) -> Self {
+        self.window = frames;
+        self
+    }
+
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+}
+
+impl egui::Widget for FrameTimeGraph<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.vertical(|ui| {
+            if let Some(stats) = self.ui_state.frame_stats(self.window) {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} fps  avg {:.2}  p95 {:.2}  p99 {:.2}  max {:.2} ms",
+                        self.ui_state.fps(),
+                        stats.avg,
+                        stats.p95,
+                        stats.p99,
+                        stats.max
+                    ))
+                    .monospace(),
+                );
+            }
+            let frame_times = self.ui_state.frame_times();
+            graph(
+                ui,
+                self.height,
+                FRAME_HISTORY_LEN,
+                // Keep 60 fps visible as a reference even when every frame is faster
+                1000.0 / 60.0,
+                &[(
+                    ui.visuals().text_color(),
+                    frame_times.iter().copied().collect(),
+                )],
+            );
+        })
+        .response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_of_no_frames() {
+        assert_eq!(FrameStats::new([]), None);
+    }
+
+    #[test]
+    fn stats_of_shuffled_frames() {
+        // 1 to 100 milliseconds, out of order
+        let frame_times = (0..100).map(|i| ((i * 37) % 100 + 1) as f32);
+        let stats = FrameStats::new(frame_times).unwrap();
+        assert_eq!(
+            stats,
+            FrameStats {
+                min: 1.0,
+                avg: 50.5,
+                max: 100.0,
+                p50: 50.0,
+                p95: 95.0,
+                p99: 99.0,
+            }
+        );
+    }
+
+    #[test]
+    fn stats_of_a_single_frame() {
+        let stats = FrameStats::new([16.0]).unwrap();
+        assert_eq!(
+            (stats.min, stats.p50, stats.p99, stats.max),
+            (16.0, 16.0, 16.0, 16.0)
+        );
+    }
+
+    #[test]
+    fn fps_of_no_frames() {
+        assert_eq!(fps(&VecDeque::new()), 0);
+        assert_eq!(fps(&VecDeque::from([0.0; 10])), 0);
+    }
+
+    #[test]
+    fn fps_beyond_the_history() {
+        let frame_times = VecDeque::from([1.0; FRAME_HISTORY_LEN]);
+        assert_eq!(fps(&frame_times), 1000);
+    }
+
+    #[test]
+    fn fps_of_the_last_second() {
+        let mut frame_times = VecDeque::from([100.0; 10]);
+        frame_times.extend([10.0; 100]);
+        assert_eq!(fps(&frame_times), 100);
+        assert_eq!(fps(&VecDeque::from([1000.0 / 60.0; 120])), 60);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::{capture::Image, controller::ControllerTrait, headless::HeadlessRunner};
+use bytemuck::AnyBitPattern;
+use std::path::PathBuf;
+
+/// Set to write references instead of comparing against them
+const UPDATE_VAR: &str = "UPDATE_GOLDEN";
+
+/// Renders frames for [`Golden::check_frames`], either [`HeadlessRunner`] or [`crate::CpuRunner`]
+/// For a software GPU run headless with `RunnerConfig::adapter(|info| info.device_type == wgpu::DeviceType::Cpu)`
+pub trait GoldenRunner {
+    fn render_frame(&mut self) -> Image;
+
+    /// Contents of the buffer described at `set` and `binding`, panics for other kinds of bindings
+    fn read_buffer(&self, set: usize, binding: usize) -> Vec<u8>;
+}
+
+impl<C: ControllerTrait> GoldenRunner for HeadlessRunner<C> {
+    fn render_frame(&mut self) -> Image {
+        HeadlessRunner::render_frame(self)
+    }
+
+    fn read_buffer(&self, set: usize, binding: usize) -> Vec<u8> {
+        HeadlessRunner::read_buffer(self, set, binding)
+    }
+}
+
+#[cfg(feature = "cpu")]
+impl<C: crate::CpuController, S: crate::CpuShader<C>> GoldenRunner for crate::CpuRunner<C, S> {
+    fn render_frame(&mut self) -> Image {
+        crate::CpuRunner::render_frame(self)
+    }
+
+    fn read_buffer(&self, set: usize, binding: usize) -> Vec<u8> {
+        self.bindings().bytes(set, binding).to_vec()
+    }
+}
+
+/// Compares frames and buffers against references stored in a directory, images as `{name}.png` and buffers as `{name}.bin`
+/// Run with `UPDATE_GOLDEN=1` to write the references instead, e.g. for a new test or after an intended change
+/// ```no_run
+/// # use easy_shader_runner::{Golden, GoldenRunner};
+/// # fn test(runner: &mut impl GoldenRunner) {
+/// let golden = Golden::new("tests/golden").tolerance(2);
+/// golden
+///     .check_frames("glider", runner, 60)
+///     .unwrap_or_else(|err| panic!("{err}"));
+/// # }
+/// ```
+pub struct Golden {
+    dir: PathBuf,
+    output_dir: PathBuf,
+    tolerance: u8,
+    max_mismatches: usize,
+}
+
+impl Golden {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self {
+            output_dir: dir.join("failures"),
+            dir,
+            tolerance: 0,
+            max_mismatches: 0,
+        }
+    }
+
+    /// Where the output of failed checks and diff images go, `failures` inside the reference directory by default
+    /// Integration tests can pass `env!("CARGO_TARGET_TMPDIR")` to keep them out of the source tree
+    pub fn output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = dir.into();
+        self
+    }
+
+    /// Largest difference allowed in any channel of a pixel, 0 by default
+    /// A little tolerance lets the same reference pass on different GPUs and on the CPU
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Pixels or buffer elements allowed to be off by more than the tolerance, 0 by default
+    pub fn max_mismatches(mut self, max_mismatches: usize) -> Self {
+        self.max_mismatches = max_mismatches;
+        self
+    }
+
+    /// Render `frames` frames and compare the last one against `{name}.png`, returning it
+    pub fn check_frames(
+        &self,
+        name: &str,
+        runner: &mut impl GoldenRunner,
+        frames: u32,
+    ) -> Result<Image, GoldenError> {
+        assert!(frames > 0, "Render at least one frame");
+        let mut image = runner.render_frame();
+        for _ in 1..frames {
+            image = runner.render_frame();
+        }
+        self.check_image(name, &image)?;
+        Ok(image)
+    }
+
+    /// Compare `image` against `{name}.png`
+    /// On a mismatch `{name}.actual.png` and `{name}.diff.png` are written to the output directory,
+    /// the diff shows the reference faded with mismatching pixels in red
+    pub fn check_image(&self, name: &str, image: &Image) -> Result<(), GoldenError> {
+        let path = self.dir.join(format!("{name}.png"));
+        if update_references() {
+            std::fs::create_dir_all(&self.dir)?;
+            image.save_png(&path)?;
+            return Ok(());
+        }
+        if !path.exists() {
+            self.save_actual_image(name, image)?;
+            return Err(GoldenError::Missing { path });
+        }
+        let expected = Image::load_png(&path)?;
+        if expected.size != image.size {
+            let actual = self.save_actual_image(name, image)?;
+            return Err(GoldenError::SizeMismatch {
+                name: name.to_owned(),
+                expected: expected.size,
+                actual: image.size,
+                output: actual,
+            });
+        }
+
+        let mut mismatches = 0;
+        let mut max_difference = 0;
+        let mut diff = Vec::with_capacity(image.data.len());
+        for (expected, actual) in expected
+            .data
+            .chunks_exact(4)
+            .zip(image.data.chunks_exact(4))
+        {
+            let difference = (0..4)
+                .map(|i| expected[i].abs_diff(actual[i]))
+                .max()
+                .unwrap_or_default();
+            max_difference = max_difference.max(difference);
+            if difference > self.tolerance {
+                mismatches += 1;
+                diff.extend([255, 0, 0, 255]);
+            } else {
+                let luma = expected[..3].iter().map(|&c| c as u32).sum::<u32>() / 3;
+                let faded = (luma / 4) as u8;
+                diff.extend([faded, faded, faded, 255]);
+            }
+        }
+        if mismatches <= self.max_mismatches {
+            return Ok(());
+        }
+        self.save_actual_image(name, image)?;
+        let diff_path = self.output_dir.join(format!("{name}.diff.png"));
+        Image {
+            size: image.size,
+            data: diff,
+        }
+        .save_png(&diff_path)?;
+        Err(GoldenError::ImageMismatch {
+            name: name.to_owned(),
+            mismatches,
+            max_difference,
+            diff: diff_path,
+        })
+    }
+
+    /// Compare `bytes` exactly against `{name}.bin`
+    pub fn check_buffer(&self, name: &str, bytes: &[u8]) -> Result<(), GoldenError> {
+        self.check_buffer_with::<u8>(name, bytes, |expected, actual| expected == actual)
+    }
+
+    /// Compare `bytes` against `{name}.bin` as elements of `T`, `matches` tells whether an element is close enough
+    /// On a mismatch `{name}.actual.bin` is written to the output directory
+    /// ```no_run
+    /// # use easy_shader_runner::Golden;
+    /// # fn test(golden: &Golden, bytes: &[u8]) -> Result<(), easy_shader_runner::GoldenError> {
+    /// golden.check_buffer_with::<f32>("velocities", bytes, |expected, actual| {
+    ///     (expected - actual).abs() < 1e-4
+    /// })
+    /// # }
+    /// ```
+    pub fn check_buffer_with<T: AnyBitPattern>(
+        &self,
+        name: &str,
+        bytes: &[u8],
+        matches: impl Fn(T, T) -> bool,
+    ) -> Result<(), GoldenError> {
+        let path = self.dir.join(format!("{name}.bin"));
+        if update_references() {
+            std::fs::create_dir_all(&self.dir)?;
+            std::fs::write(&path, bytes)?;
+            return Ok(());
+        }
+        if !path.exists() {
+            self.save_actual_buffer(name, bytes)?;
+            return Err(GoldenError::Missing { path });
+        }
+        let expected = std::fs::read(&path)?;
+        let size = std::mem::size_of::<T>();
+        let mismatching = expected
+            .chunks_exact(size)
+            .zip(bytes.chunks_exact(size))
+            .enumerate()
+            .filter(|(_, (expected, actual))| {
+                !matches(
+                    bytemuck::pod_read_unaligned(expected),
+                    bytemuck::pod_read_unaligned(actual),
+                )
+            })
+            .map(|(i, _)| i);
+        let (mut first, mut mismatches) = (None, 0);
+        for i in mismatching {
+            first.get_or_insert(i);
+            mismatches += 1;
+        }
+        if expected.len() == bytes.len() && mismatches <= self.max_mismatches {
+            return Ok(());
+        }
+        let actual = self.save_actual_buffer(name, bytes)?;
+        Err(GoldenError::BufferMismatch {
+            name: name.to_owned(),
+            expected_len: expected.len(),
+            actual_len: bytes.len(),
+            mismatches,
+            first,
+            output: actual,
+        })
+    }
+
+    fn save_actual_image(&self, name: &str, image: &Image) -> Result<PathBuf, GoldenError> {
+        std::fs::create_dir_all(&self.output_dir)?;
+        let path = self.output_dir.join(format!("{name}.actual.png"));
+        image.save_png(&path)?;
+        Ok(path)
+    }
+
+    fn save_actual_buffer(&self, name: &str, bytes: &[u8]) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.output_dir)?;
+        let path = self.output_dir.join(format!("{name}.actual.bin"));
+        std::fs::write(&path, bytes)?;
+        Ok(path)
+    }
+}
+
+fn update_references() -> bool {
+    std::env::var_os(UPDATE_VAR).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Returned when output doesn't match its reference, the message says where the output was written
+#[derive(Debug)]
+pub enum GoldenError {
+    /// No reference yet, the output was still written to the output directory
+    Missing {
+        path: PathBuf,
+    },
+    SizeMismatch {
+        name: String,
+        expected: glam::UVec2,
+        actual: glam::UVec2,
+        output: PathBuf,
+    },
+    ImageMismatch {
+        name: String,
+        /// Pixels off by more than the tolerance
+        mismatches: usize,
+        /// Largest difference in any channel
+        max_difference: u8,
+        diff: PathBuf,
+    },
+    BufferMismatch {
+        name: String,
+        expected_len: usize,
+        actual_len: usize,
+        /// Elements that didn't match, only counting those both buffers have
+        mismatches: usize,
+        /// Index of the first element that didn't match
+        first: Option<usize>,
+        output: PathBuf,
+    },
+    Io(std::io::Error),
+    Decode(png::DecodingError),
+    Encode(png::EncodingError),
+}
+
+impl std::fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { path } => write!(
+                f,
+                "no reference at {}, run with {UPDATE_VAR}=1 to write it",
+                path.display()
+            ),
+            Self::SizeMismatch {
+                name,
+                expected,
+                actual,
+                output,
+            } => write!(
+                f,
+                "`{name}` is {}x{} but the reference is {}x{}, see {}",
+                actual.x,
+                actual.y,
+                expected.x,
+                expected.y,
+                output.display()
+            ),
+            Self::ImageMismatch {
+                name,
+                mismatches,
+                max_difference,
+                diff,
+            } => write!(
+                f,
+                "`{name}` has {mismatches} mismatching pixels, differing by up to {max_difference}, see {}",
+                diff.display()
+            ),
+            Self::BufferMismatch {
+                name,
+                expected_len,
+                actual_len,
+                mismatches,
+                first,
+                output,
+            } => {
+                write!(f, "`{name}`")?;
+                if expected_len != actual_len {
+                    write!(f, " is {actual_len} bytes instead of {expected_len} and")?;
+                }
+                write!(f, " has {mismatches} mismatching elements")?;
+                if let Some(first) = first {
+                    write!(f, " starting at {first}")?;
+                }
+                write!(f, ", see {}", output.display())
+            }
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Decode(err) => write!(f, "{err}"),
+            Self::Encode(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<std::io::Error> for GoldenError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for GoldenError {
+    fn from(err: png::DecodingError) -> Self {
+        Self::Decode(err)
+    }
+}
+
+impl From<png::EncodingError> for GoldenError {
+    fn from(err: png::EncodingError) -> Self {
+        Self::Encode(err)
+    }
+}
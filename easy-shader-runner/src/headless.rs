@@ -0,0 +1,201 @@
+use crate::{
+    capture::{read_buffer, read_texture, Image},
+    clock::Clock,
+    config::RunnerConfig,
+    context::GraphicsContext,
+    controller::ControllerTrait,
+    render_pass::RenderPass,
+    shader_modules::ShaderModules,
+};
+#[cfg(feature = "input-recording")]
+use crate::{
+    input_log::{InputReplay, LoggedInput},
+    key_bindings::{KeyAction, KeyBindings},
+    winit::event::ElementState,
+};
+
+/// Drives a controller without a window, rendering into an offscreen texture
+/// [`ControllerTrait::ui`] is never called and the window options of [`RunnerConfig`] are ignored
+/// Every frame advances the clock by one timestep, so the same controller renders the same frames
+/// Input can be replayed with [`Self::replay_input`] when the `input-recording` feature is enabled
+pub struct HeadlessRunner<C: ControllerTrait> {
+    rpass: RenderPass,
+    ctx: GraphicsContext,
+    controller: C,
+    target: wgpu::Texture,
+    clock: Clock,
+    #[cfg(feature = "input-recording")]
+    replay: Option<(InputReplay, KeyBindings)>,
+    /// Last value passed to [`ControllerTrait::pointer_over_viewport`] while replaying
+    #[cfg(feature = "input-recording")]
+    pointer_over_viewport: bool,
+}
+
+impl<C: ControllerTrait> HeadlessRunner<C> {
+    pub fn new(
+        mut controller: C,
+        shader_bytes: &[u8],
+        size: glam::UVec2,
+        config: RunnerConfig,
+    ) -> Self {
+        let ctx = futures::executor::block_on(GraphicsContext::new_headless(size, &config));
+        let shader = ShaderModules::Single(shader_bytes.into());
+        let rpass = RenderPass::new(&ctx, &shader, &mut controller, config.clear_color);
+        let target = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Target"),
+            size: wgpu::Extent3d {
+                width: ctx.config.width,
+                height: ctx.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ctx.config.format,
+            usage: ctx.config.usage,
+            view_formats: &[],
+        });
+        Self {
+            rpass,
+            ctx,
+            controller,
+            target,
+            clock: Clock::new(config.timestep, config.paused),
+            #[cfg(feature = "input-recording")]
+            replay: None,
+            #[cfg(feature = "input-recording")]
+            pointer_over_viewport: false,
+        }
+    }
+
+    /// Feed recorded input to the controller before each frame, frames then take as long as they did when recorded
+    /// Resizes are ignored and of the key bindings only pausing and stepping apply
+    #[cfg(feature = "input-recording")]
+    pub fn replay_input(&mut self, replay: InputReplay) {
+        self.replay = Some((replay, self.controller.key_bindings()));
+    }
+
+    /// Deliver the input of the next replayed frame and return how long it took
+    #[cfg(feature = "input-recording")]
+    fn replay_frame(&mut self) -> Option<f32> {
+        let (replay, key_bindings) = self.replay.as_mut()?;
+        let Some(frame) = replay.next_frame() else {
+            self.replay = None;
+            return None;
+        };
+        for input in frame.inputs {
+            match input {
+                LoggedInput::Frame { .. } | LoggedInput::Resize { .. } => {}
+                LoggedInput::Modifiers { state } => self.controller.modifiers_changed(state),
+                LoggedInput::Touch { id, phase, x, y } => {
+                    self.controller.touch(id, phase, glam::dvec2(x, y))
+                }
+                LoggedInput::CursorEntered => self.controller.cursor_entered(),
+                LoggedInput::CursorLeft => self.controller.cursor_left(),
+                LoggedInput::Focused { focused } => self.controller.focused(focused),
+                LoggedInput::MouseMove { x, y } => self.controller.mouse_move(glam::dvec2(x, y)),
+                LoggedInput::MouseScroll { x, y } => {
+                    self.controller.mouse_scroll(glam::dvec2(x, y))
+                }
+                LoggedInput::MouseInput { state, button } => {
+                    self.controller.mouse_input(state, button)
+                }
+                LoggedInput::Keyboard { key } => match key_bindings.action(&key.logical_key) {
+                    None => self.controller.keyboard_input(key),
+                    Some(_) if key.state != ElementState::Pressed || key.repeat => {}
+                    Some(KeyAction::TogglePause) => self.clock.set_paused(!self.clock.is_paused()),
+                    Some(KeyAction::Step) => self.clock.step(),
+                    Some(_) => {}
+                },
+            }
+        }
+        // A window finds out while rendering, before the shader runs
+        if frame.pointer_over_viewport != self.pointer_over_viewport {
+            self.pointer_over_viewport = frame.pointer_over_viewport;
+            self.controller
+                .pointer_over_viewport(frame.pointer_over_viewport);
+        }
+        Some(frame.delta)
+    }
+
+    /// Render a single frame and read it back
+    /// Compute runs after rendering, the same as in a window
+    pub fn render_frame(&mut self) -> Image {
+        let view = self
+            .target
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        #[cfg(feature = "input-recording")]
+        let delta = self.replay_frame().unwrap_or(self.clock.timestep());
+        #[cfg(not(feature = "input-recording"))]
+        let delta = self.clock.timestep();
+        let time = self.clock.tick(delta);
+        self.rpass
+            .render_offscreen(&self.ctx, &view, &mut self.controller, time);
+        let image = read_texture(&self.ctx, &self.target);
+        #[cfg(feature = "compute")]
+        self.controller.update(
+            |entry, dimensions, threads, push_constants| {
+                self.rpass.compute(
+                    &self.ctx,
+                    entry,
+                    dimensions,
+                    threads,
+                    bytemuck::bytes_of(push_constants),
+                );
+            },
+            // Nothing to keep responsive, so never cut the update short
+            f32::INFINITY,
+            time,
+        );
+        image
+    }
+
+    pub fn controller(&self) -> &C {
+        &self.controller
+    }
+
+    pub fn controller_mut(&mut self) -> &mut C {
+        &mut self.controller
+    }
+
+    /// Recreate the resource at `set` and `binding` from [`ControllerTrait::describe_buffers`] right away
+    /// The same as [`crate::UiState::reallocate_buffer`] in a window, the new handle is passed to `receive_buffers` or `receive_textures`
+    pub fn reallocate_buffer(&mut self, set: usize, binding: usize) {
+        self.rpass
+            .reallocate(&self.ctx, &mut self.controller, &[(set, binding)]);
+    }
+
+    /// Read back the buffer described at `set` and `binding`, panics for other kinds of bindings
+    pub fn read_buffer(&self, set: usize, binding: usize) -> Vec<u8> {
+        let buffer = self
+            .rpass
+            .buffer(set, binding)
+            .unwrap_or_else(|| panic!("No buffer bound at set {set}, binding {binding}"));
+        read_buffer(&self.ctx, buffer)
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
+    pub fn graphics_context(&self) -> &GraphicsContext {
+        &self.ctx
+    }
+}
+
+/// Render `frames` frames at `size` without opening a window
+pub fn run_headless<C: ControllerTrait>(
+    controller: C,
+    shader_bytes: &[u8],
+    size: glam::UVec2,
+    frames: u32,
+    config: RunnerConfig,
+) -> Vec<Image> {
+    crate::setup_logging();
+    let mut runner = HeadlessRunner::new(controller, shader_bytes, size, config);
+    (0..frames).map(|_| runner.render_frame()).collect()
+}
@@ -0,0 +1,168 @@
+use crate::key_bindings::KeyInput;
+use egui_winit::winit::{
+    event::{ElementState, MouseButton, TouchPhase},
+    keyboard::ModifiersState,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// Input as it reached the controller, positions are relative to the shader viewport
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "input", rename_all = "snake_case")]
+pub(crate) enum LoggedInput {
+    /// The frame was rendered after ticking the clock by `delta` real seconds, see [`crate::Clock`]
+    /// Logged after rendering, since that's when the pointer is known to be over the viewport or not
+    Frame {
+        delta: f32,
+        pointer_over_viewport: bool,
+    },
+    /// The window was resized, replays request the same inner size
+    Resize {
+        width: u32,
+        height: u32,
+    },
+    MouseMove {
+        x: f64,
+        y: f64,
+    },
+    MouseScroll {
+        x: f64,
+        y: f64,
+    },
+    MouseInput {
+        state: ElementState,
+        button: MouseButton,
+    },
+    Keyboard {
+        key: KeyInput,
+    },
+    Modifiers {
+        state: ModifiersState,
+    },
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        x: f64,
+        y: f64,
+    },
+    CursorEntered,
+    CursorLeft,
+    Focused {
+        focused: bool,
+    },
+}
+
+/// One line of an input log
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Index of the frame the input is delivered before, or of the frame itself for [`LoggedInput::Frame`]
+    frame: u64,
+    #[serde(flatten)]
+    input: LoggedInput,
+}
+
+/// Writes input as JSON lines, see [`crate::RunnerConfig::record_input`]
+pub(crate) struct InputRecorder {
+    writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// Flushed after every frame, so a crash loses at most the input of the frame it happened in
+    pub fn record(&mut self, frame: u64, input: LoggedInput) -> std::io::Result<()> {
+        let end_of_frame = matches!(input, LoggedInput::Frame { .. });
+        serde_json::to_writer(&mut self.writer, &Entry { frame, input })?;
+        self.writer.write_all(b"\n")?;
+        if end_of_frame {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Input read back from a file written by [`crate::RunnerConfig::record_input`]
+/// Replay it with [`crate::RunnerConfig::replay_input`] or [`crate::HeadlessRunner::replay_input`]
+pub struct InputReplay {
+    entries: VecDeque<Entry>,
+}
+
+impl InputReplay {
+    /// Fails if a line isn't valid or its frame index is out of sequence,
+    /// which means frames were lost or reordered and the replay would drift from the recording
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries = VecDeque::new();
+        let mut expected_frame = None;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = |message| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("line {}: {message}", i + 1),
+                )
+            };
+            let entry: Entry =
+                serde_json::from_str(&line).map_err(|err| invalid(err.to_string()))?;
+            let expected = *expected_frame.get_or_insert(entry.frame);
+            if entry.frame != expected {
+                return Err(invalid(format!(
+                    "recorded for frame {} where frame {expected} was expected, the log is out of sync",
+                    entry.frame
+                )));
+            }
+            if let LoggedInput::Frame { .. } = entry.input {
+                expected_frame = Some(expected + 1);
+            }
+            entries.push_back(entry);
+        }
+        Ok(Self { entries })
+    }
+
+    /// The next recorded frame, `None` once every frame is replayed
+    pub(crate) fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let mut inputs = vec![];
+        while let Some(entry) = self.entries.pop_front() {
+            match entry.input {
+                LoggedInput::Frame {
+                    delta,
+                    pointer_over_viewport,
+                } => {
+                    return Some(RecordedFrame {
+                        inputs,
+                        delta,
+                        pointer_over_viewport,
+                    })
+                }
+                input => inputs.push(input),
+            }
+        }
+        None
+    }
+}
+
+pub(crate) struct RecordedFrame {
+    /// Delivered before the frame
+    pub inputs: Vec<LoggedInput>,
+    /// Real seconds the frame took
+    pub delta: f32,
+    pub pointer_over_viewport: bool,
+}
+
+/// What [`crate::RunnerConfig::record_input`] or [`crate::RunnerConfig::replay_input`] set up
+pub(crate) enum InputLog {
+    Recording(InputRecorder),
+    Replaying(InputReplay),
+}
@@ -0,0 +1,120 @@
+use egui_winit::winit::{
+    event::{ElementState, KeyEvent},
+    keyboard::{Key, KeyLocation, NamedKey, PhysicalKey, SmolStr},
+};
+
+/// A key press or release, the parts of [`KeyEvent`] that can be recorded and replayed
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    all(feature = "input-recording", not(target_arch = "wasm32")),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct KeyInput {
+    pub physical_key: PhysicalKey,
+    pub logical_key: Key,
+    pub text: Option<SmolStr>,
+    pub location: KeyLocation,
+    pub state: ElementState,
+    pub repeat: bool,
+}
+
+impl From<KeyEvent> for KeyInput {
+    fn from(event: KeyEvent) -> Self {
+        Self {
+            physical_key: event.physical_key,
+            logical_key: event.logical_key,
+            text: event.text,
+            location: event.location,
+            state: event.state,
+            repeat: event.repeat,
+        }
+    }
+}
+
+/// Something the runner does itself when a bound key is pressed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    /// Exit the event loop
+    Quit,
+    /// Show or hide everything drawn by [`crate::ControllerTrait::ui`], see [`crate::UiState::show_ui`]
+    ToggleUi,
+    /// Same as [`crate::UiState::screenshot`] without the UI
+    #[cfg(not(target_arch = "wasm32"))]
+    Screenshot,
+    /// Switch between windowed and borderless fullscreen
+    ToggleFullscreen,
+    /// Pause or resume the [`crate::Clock`]
+    TogglePause,
+    /// Advance the [`crate::Clock`] by a single timestep, see [`crate::Clock::step`]
+    Step,
+}
+
+/// Keys the runner handles before [`crate::ControllerTrait::keyboard_input`], see [`crate::ControllerTrait::key_bindings`]
+/// Presses and releases of bound keys never reach the controller, every other key does
+/// ```
+/// # use easy_shader_runner::{winit::keyboard::{Key, NamedKey}, KeyAction, KeyBindings};
+/// // Keep Escape for the controller and quit with q instead
+/// let bindings = KeyBindings::default()
+///     .unbind(KeyAction::Quit)
+///     .bind(Key::Character("q".into()), KeyAction::Quit);
+/// assert_eq!(bindings.action(&Key::Named(NamedKey::Escape)), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KeyBindings {
+    bindings: Vec<(Key, KeyAction)>,
+}
+
+impl Default for KeyBindings {
+    /// Escape quits, F1 toggles the UI, F11 toggles fullscreen and F12 takes a screenshot
+    fn default() -> Self {
+        let bindings = Self::empty()
+            .bind(NamedKey::Escape, KeyAction::Quit)
+            .bind(NamedKey::F1, KeyAction::ToggleUi)
+            .bind(NamedKey::F11, KeyAction::ToggleFullscreen);
+        #[cfg(not(target_arch = "wasm32"))]
+        let bindings = bindings.bind(NamedKey::F12, KeyAction::Screenshot);
+        bindings
+    }
+}
+
+impl KeyBindings {
+    /// No bound keys, every key reaches the controller
+    pub fn empty() -> Self {
+        Self { bindings: vec![] }
+    }
+
+    /// Bind the logical `key` to `action`, replacing what `key` was bound to
+    /// Character keys match exactly, so `"q"` and `"Q"` are different keys
+    pub fn bind(mut self, key: impl Into<Key>, action: KeyAction) -> Self {
+        let key = key.into();
+        self.bindings.retain(|(bound, _)| *bound != key);
+        self.bindings.push((key, action));
+        self
+    }
+
+    /// Unbind every key bound to `action`
+    pub fn unbind(mut self, action: KeyAction) -> Self {
+        self.bindings.retain(|(_, bound)| *bound != action);
+        self
+    }
+
+    /// Unbind `key` so it reaches the controller
+    pub fn unbind_key(mut self, key: impl Into<Key>) -> Self {
+        let key = key.into();
+        self.bindings.retain(|(bound, _)| *bound != key);
+        self
+    }
+
+    pub fn action(&self, key: &Key) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find_map(|(bound, action)| (bound == key).then_some(*action))
+    }
+
+    /// Keys bound to `action`, e.g. to show them in the UI
+    pub fn keys(&self, action: KeyAction) -> impl Iterator<Item = &Key> {
+        self.bindings
+            .iter()
+            .filter_map(move |(key, bound)| (*bound == action).then_some(key))
+    }
+}
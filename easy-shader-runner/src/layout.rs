@@ -0,0 +1,253 @@
+use crate::{
+    bind_group_buffer::{BindingKind, BufferDescriptor},
+    reflection::{stage_name, ReflectedBinding, ReflectedKind},
+};
+
+/// Whether the layout of the described sets is used as described or patched from the shader
+/// The layout is never derived from the shader alone, every binding has to be described to have a resource
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LayoutSource {
+    /// Use [`BufferDescriptor`] as is, the shader is checked against it
+    #[default]
+    Described,
+    /// Overwrite the visibility, read-only-ness and storage texture access of described bindings with how the shader uses them
+    /// Kinds, contents, sizes and formats still come from [`BufferDescriptor`] and are checked against the shader
+    PatchedFromShader,
+}
+
+/// A described binding that disagrees with the shader module
+#[derive(Clone, Debug)]
+pub struct BindingMismatch {
+    pub set: u32,
+    pub binding: u32,
+    pub message: String,
+}
+
+impl std::fmt::Display for BindingMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "descriptor_set = {}, binding = {}: {}",
+            self.set, self.binding, self.message
+        )
+    }
+}
+
+/// Layout entries of the described sets
+/// Without reflection, e.g. for modules that couldn't be parsed, the descriptors are used as is
+/// `reserved_sets` follow the described ones and are bound by the runner, for pass targets and emulated push constants
+pub(crate) fn layout_entries(
+    buffer_descriptors2: &[Vec<BufferDescriptor>],
+    reflected: Option<&[ReflectedBinding]>,
+    source: LayoutSource,
+    reserved_sets: usize,
+) -> Result<Vec<Vec<wgpu::BindGroupLayoutEntry>>, Vec<BindingMismatch>> {
+    let mut mismatches = vec![];
+    let entries = buffer_descriptors2
+        .iter()
+        .enumerate()
+        .map(|(set, descriptors)| {
+            descriptors
+                .iter()
+                .enumerate()
+                .map(|(binding, descriptor)| {
+                    let mut entry = wgpu::BindGroupLayoutEntry {
+                        binding: binding as u32,
+                        visibility: descriptor.shader_stages,
+                        ty: descriptor.binding_type(),
+                        count: None,
+                    };
+                    let reflected = reflected
+                        .into_iter()
+                        .flatten()
+                        .find(|b| (b.set as usize, b.binding as usize) == (set, binding));
+                    if let Some(reflected) = reflected {
+                        let mut mismatch = |message| {
+                            mismatches.push(BindingMismatch {
+                                set: set as u32,
+                                binding: binding as u32,
+                                message,
+                            })
+                        };
+                        if let Err(message) = check(descriptor, reflected) {
+                            mismatch(message);
+                        } else if source == LayoutSource::PatchedFromShader {
+                            apply(&mut entry, reflected);
+                        } else if let Err(message) = check_described(descriptor, reflected) {
+                            mismatch(message);
+                        }
+                    }
+                    entry
+                })
+                .collect()
+        })
+        .collect();
+
+    let described_sets = buffer_descriptors2.len();
+    for reflected in reflected.into_iter().flatten() {
+        let set = reflected.set as usize;
+        let missing = match buffer_descriptors2.get(set) {
+            Some(descriptors) if descriptors.len() > reflected.binding as usize => continue,
+            Some(_) => "nothing is described here",
+            None if set < described_sets + reserved_sets => continue,
+            None => "nothing is described in this set",
+        };
+        mismatches.push(BindingMismatch {
+            set: reflected.set,
+            binding: reflected.binding,
+            message: format!(
+                "the shader uses {} as a {} but {missing}",
+                display_name(reflected),
+                reflected.kind.name()
+            ),
+        });
+    }
+
+    if mismatches.is_empty() {
+        Ok(entries)
+    } else {
+        mismatches.sort_by_key(|m| (m.set, m.binding));
+        Err(mismatches)
+    }
+}
+
+/// Checks that hold regardless of the [`LayoutSource`]
+fn check(descriptor: &BufferDescriptor, reflected: &ReflectedBinding) -> Result<(), String> {
+    let described = match descriptor.kind {
+        BindingKind::StorageBuffer => "storage buffer",
+        BindingKind::UniformBuffer => "uniform buffer",
+        BindingKind::Texture { .. } => "texture",
+        BindingKind::StorageTexture { .. } => "storage texture",
+        BindingKind::Sampler { .. } => "sampler",
+    };
+    let name = display_name(reflected);
+    match (descriptor.kind, reflected.kind) {
+        (_, ReflectedKind::Unsupported) => Err(format!(
+            "the shader declares {name} with a binding type that can't be described"
+        )),
+        (BindingKind::StorageBuffer, ReflectedKind::StorageBuffer { .. })
+        | (BindingKind::UniformBuffer, ReflectedKind::UniformBuffer)
+        | (BindingKind::Sampler { .. }, ReflectedKind::Sampler) => Ok(()),
+        (
+            BindingKind::Texture { .. },
+            ReflectedKind::Texture {
+                view_dimension,
+                multisampled,
+            },
+        ) => {
+            if view_dimension != wgpu::TextureViewDimension::D2 {
+                Err(format!(
+                    "the shader declares {name} as a {view_dimension:?} texture, only D2 can be described"
+                ))
+            } else if multisampled {
+                Err(format!(
+                    "the shader declares {name} as multisampled, which can't be described"
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        (
+            BindingKind::StorageTexture { format, .. },
+            ReflectedKind::StorageTexture {
+                view_dimension,
+                format: reflected_format,
+                ..
+            },
+        ) => {
+            if view_dimension != wgpu::TextureViewDimension::D2 {
+                Err(format!(
+                    "the shader declares {name} as a {view_dimension:?} storage texture, only D2 can be described"
+                ))
+            } else if reflected_format != format {
+                Err(format!(
+                    "described with format {format:?} but the shader declares {name} as {reflected_format:?}"
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        (_, kind) => Err(format!(
+            "described as a {described} but the shader declares {name} as a {}",
+            kind.name()
+        )),
+    }
+}
+
+/// Checks for properties that [`LayoutSource::PatchedFromShader`] would overwrite
+fn check_described(
+    descriptor: &BufferDescriptor,
+    reflected: &ReflectedBinding,
+) -> Result<(), String> {
+    let name = display_name(reflected);
+    match (descriptor.kind, reflected.kind) {
+        (BindingKind::StorageBuffer, ReflectedKind::StorageBuffer { read_only: false })
+            if descriptor.read_only =>
+        {
+            return Err(format!(
+                "described as read-only but the shader declares {name} as writable"
+            ));
+        }
+        (
+            BindingKind::StorageTexture { access, .. },
+            ReflectedKind::StorageTexture {
+                access: Some(reflected_access),
+                ..
+            },
+        ) if access != reflected_access => {
+            return Err(format!(
+                "described with {access:?} access but the shader declares {name} as {reflected_access:?}"
+            ));
+        }
+        _ => {}
+    }
+    if !descriptor.shader_stages.contains(reflected.stages) {
+        return Err(format!(
+            "visible to {} but {name} is used by the {} stage",
+            stage_names(descriptor.shader_stages),
+            stage_names(reflected.stages - descriptor.shader_stages)
+        ));
+    }
+    Ok(())
+}
+
+fn apply(entry: &mut wgpu::BindGroupLayoutEntry, reflected: &ReflectedBinding) {
+    entry.visibility = reflected.stages;
+    match (&mut entry.ty, reflected.kind) {
+        (
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                ..
+            },
+            ReflectedKind::StorageBuffer {
+                read_only: reflected_read_only,
+            },
+        ) => *read_only = reflected_read_only,
+        (
+            wgpu::BindingType::StorageTexture { access, .. },
+            ReflectedKind::StorageTexture {
+                access: Some(reflected_access),
+                ..
+            },
+        ) => *access = reflected_access,
+        _ => {}
+    }
+}
+
+fn display_name(reflected: &ReflectedBinding) -> String {
+    match &reflected.name {
+        Some(name) => format!("`{name}`"),
+        None => "it".to_owned(),
+    }
+}
+
+fn stage_names(stages: wgpu::ShaderStages) -> String {
+    if stages.is_empty() {
+        return "no stage".to_owned();
+    }
+    stages
+        .iter()
+        .map(stage_name)
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
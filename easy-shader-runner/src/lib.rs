@@ -1,11 +1,35 @@
 use egui_winit::winit::event_loop::EventLoop;
-use std::borrow::Cow;
+use shader_modules::ShaderModules;
 use user_event::CustomEvent;
 
-pub use bind_group_buffer::BufferDescriptor;
-pub use controller::ControllerTrait;
-pub use ui::UiState;
+pub use bind_group_buffer::{BindingKind, BufferDescriptor};
+pub use capture::Image;
+pub use clock::{Clock, FrameTime};
+pub use config::RunnerConfig;
 pub use context::GraphicsContext;
+#[cfg(feature = "compute")]
+pub use controller::ComputeEntry;
+pub use controller::{ControllerTrait, EntryPoints};
+#[cfg(feature = "cpu")]
+pub use cpu::{run_cpu, CpuBindings, CpuController, CpuRunner, CpuShader};
+pub use frame_stats::{FrameStats, FrameTimeGraph};
+#[cfg(all(feature = "golden", not(target_arch = "wasm32")))]
+pub use golden::{Golden, GoldenError, GoldenRunner};
+#[cfg(not(target_arch = "wasm32"))]
+pub use headless::{run_headless, HeadlessRunner};
+#[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+pub use input_log::InputReplay;
+pub use key_bindings::{KeyAction, KeyBindings, KeyInput};
+pub use layout::{BindingMismatch, LayoutSource};
+pub use passes::PassDescriptor;
+pub use profiler::{PassTimings, TimingSource};
+#[cfg(not(target_arch = "wasm32"))]
+pub use recording::{RecordingFormat, RecordingOptions};
+pub use reflection::PipelineError;
+#[cfg(feature = "image")]
+pub use texture::TextureData;
+pub use transfer::{DroppedFile, Paste};
+pub use ui::UiState;
 
 pub use egui_wgpu::wgpu;
 pub use egui_winit::egui;
@@ -13,20 +37,44 @@ pub use egui_winit::winit;
 
 mod app;
 mod bind_group_buffer;
+mod capture;
+mod clock;
+mod config;
 mod context;
 mod controller;
-mod fps_counter;
+#[cfg(feature = "cpu")]
+mod cpu;
+mod frame_stats;
+#[cfg(all(feature = "golden", not(target_arch = "wasm32")))]
+mod golden;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+#[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+mod input_log;
+mod key_bindings;
+mod layout;
+mod passes;
+mod profiler;
+mod push_constants;
+#[cfg(not(target_arch = "wasm32"))]
+mod recording;
+mod reflection;
 mod render_pass;
 #[cfg(all(
     any(feature = "runtime-compilation", feature = "hot-reload-shader"),
     not(target_arch = "wasm32")
 ))]
 mod shader;
+mod shader_modules;
+#[cfg(feature = "image")]
+mod texture;
+mod transfer;
 mod ui;
 mod user_event;
 
-const TITLE: &str = "runner";
-
+/// Build the shader crate at `shader_crate_path`, relative to the calling crate, and run it
+/// With `hot-reload-shader` the shader is built by this executable started again with the same arguments,
+/// which exits in here once the build is done, so keep side effects before this call to a minimum
 #[cfg(all(
     any(feature = "runtime-compilation", feature = "hot-reload-shader"),
     not(target_arch = "wasm32")
@@ -34,31 +82,43 @@ const TITLE: &str = "runner";
 pub fn run_with_runtime_compilation<C: ControllerTrait + Send>(
     controller: C,
     shader_crate_path: impl AsRef<std::path::Path>,
+    config: RunnerConfig,
 ) {
+    #[cfg(feature = "hot-reload-shader")]
+    shader::exit_if_build_process(&shader_crate_path);
     setup_logging();
     let event_loop = EventLoop::with_user_event().build().unwrap();
     // Build the shader before we pop open a window, since it might take a while.
-    let shader_path = shader::compile_shader(
+    let shader = shader::compile_shader(
         #[cfg(feature = "hot-reload-shader")]
         event_loop.create_proxy(),
         shader_crate_path,
     );
-    let shader_bytes = std::fs::read(shader_path).unwrap();
-    start(event_loop, controller, shader_bytes)
+    start(event_loop, controller, shader, config)
 }
 
-pub fn run_with_prebuilt_shader<C: ControllerTrait>(controller: C, shader_bytes: &'static [u8]) {
+pub fn run_with_prebuilt_shader<C: ControllerTrait>(
+    controller: C,
+    shader_bytes: &'static [u8],
+    config: RunnerConfig,
+) {
     setup_logging();
     let event_loop = EventLoop::with_user_event().build().unwrap();
-    start(event_loop, controller, shader_bytes);
+    start(
+        event_loop,
+        controller,
+        ShaderModules::Single(shader_bytes.into()),
+        config,
+    );
 }
 
 fn start<C: ControllerTrait>(
     event_loop: EventLoop<CustomEvent<C>>,
     controller: C,
-    shader_bytes: impl Into<Cow<'static, [u8]>>,
+    shader: ShaderModules<'static>,
+    config: RunnerConfig,
 ) {
-    let mut app = app::App::new(event_loop.create_proxy(), shader_bytes.into(), controller);
+    let mut app = app::App::new(event_loop.create_proxy(), shader, controller, config);
     event_loop.run_app(&mut app).unwrap()
 }
 
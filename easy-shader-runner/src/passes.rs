@@ -0,0 +1,198 @@
+/// A fragment pass rendering into an offscreen texture before the final pass
+/// The output of every pass is available to all passes, see [`crate::ControllerTrait::describe_passes`]
+pub struct PassDescriptor {
+    pub entry_point: &'static str,
+    /// A color format that can be sampled, integer and non-filterable formats make the shared sampler non-filtering
+    pub format: wgpu::TextureFormat,
+}
+
+impl PassDescriptor {
+    pub fn new(entry_point: &'static str) -> Self {
+        Self {
+            entry_point,
+            format: wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+
+    /// How later passes sample the output, panics if the format can't be used as a pass target
+    fn sample_type(&self, features: wgpu::Features) -> wgpu::TextureSampleType {
+        let (entry_point, format) = (self.entry_point, self.format);
+        assert!(
+            !format.is_depth_stencil_format(),
+            "Pass `{entry_point}` renders into {format:?}, but pass targets need a color format"
+        );
+        format.sample_type(None, Some(features)).unwrap_or_else(|| {
+            panic!("Pass `{entry_point}` renders into {format:?}, which can't be sampled")
+        })
+    }
+}
+
+/// Double buffered textures for each pass so that a pass can read
+/// its own output from the previous frame
+pub(crate) struct PassTargets {
+    pub layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    formats: Vec<wgpu::TextureFormat>,
+    views: Vec<[wgpu::TextureView; 2]>,
+    /// One per pass plus the final pass, for each parity
+    bind_groups: [Vec<wgpu::BindGroup>; 2],
+    parity: usize,
+    size: glam::UVec2,
+}
+
+impl PassTargets {
+    pub fn new(device: &wgpu::Device, passes: &[PassDescriptor]) -> Self {
+        let sample_types = passes
+            .iter()
+            .map(|pass| pass.sample_type(device.features()))
+            .collect::<Vec<_>>();
+        // The sampler is shared, so it can only filter if every target can be filtered
+        let filterable = sample_types
+            .iter()
+            .all(|ty| matches!(ty, wgpu::TextureSampleType::Float { filterable: true }));
+        let (sampler_type, filter) = if filterable {
+            (
+                wgpu::SamplerBindingType::Filtering,
+                wgpu::FilterMode::Linear,
+            )
+        } else {
+            (
+                wgpu::SamplerBindingType::NonFiltering,
+                wgpu::FilterMode::Nearest,
+            )
+        };
+        let entries = std::iter::once(wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Sampler(sampler_type),
+            count: None,
+        })
+        .chain(sample_types.iter().enumerate().map(|(i, &sample_type)| {
+            wgpu::BindGroupLayoutEntry {
+                binding: i as u32 + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    sample_type,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }
+        }))
+        .collect::<Vec<_>>();
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("passes bind_group_layout"),
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("passes sampler"),
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+        let mut targets = Self {
+            layout,
+            sampler,
+            formats: passes.iter().map(|pass| pass.format).collect(),
+            views: vec![],
+            bind_groups: [vec![], vec![]],
+            parity: 0,
+            size: glam::UVec2::ZERO,
+        };
+        targets.resize(device, glam::UVec2::ONE);
+        targets
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: glam::UVec2) {
+        let size = size.max(glam::UVec2::ONE);
+        if self.size == size {
+            return;
+        }
+        self.size = size;
+        self.views = self
+            .formats
+            .iter()
+            .enumerate()
+            .map(|(i, &format)| {
+                [0, 1].map(|parity| {
+                    device
+                        .create_texture(&wgpu::TextureDescriptor {
+                            label: Some(&format!("pass {i} target {parity}")),
+                            size: wgpu::Extent3d {
+                                width: size.x,
+                                height: size.y,
+                                depth_or_array_layers: 1,
+                            },
+                            mip_level_count: 1,
+                            sample_count: 1,
+                            dimension: wgpu::TextureDimension::D2,
+                            format,
+                            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                                | wgpu::TextureUsages::TEXTURE_BINDING,
+                            view_formats: &[],
+                        })
+                        .create_view(&wgpu::TextureViewDescriptor::default())
+                })
+            })
+            .collect();
+        self.bind_groups = [0, 1].map(|parity| {
+            (0..=self.views.len())
+                .map(|pass_index| self.create_bind_group(device, parity, pass_index))
+                .collect()
+        });
+    }
+
+    /// Passes before `pass_index` have already written this frame,
+    /// the rest are read from the previous frame
+    fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        parity: usize,
+        pass_index: usize,
+    ) -> wgpu::BindGroup {
+        let entries = std::iter::once(wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Sampler(&self.sampler),
+        })
+        .chain(self.views.iter().enumerate().map(|(i, views)| {
+            let view = if i < pass_index {
+                &views[parity]
+            } else {
+                &views[1 - parity]
+            };
+            wgpu::BindGroupEntry {
+                binding: i as u32 + 1,
+                resource: wgpu::BindingResource::TextureView(view),
+            }
+        }))
+        .collect::<Vec<_>>();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            entries: &entries,
+            label: Some(&format!("passes bind_group {pass_index}")),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    pub fn target(&self, pass_index: usize) -> &wgpu::TextureView {
+        &self.views[pass_index][self.parity]
+    }
+
+    /// Use [`Self::len`] for the final pass
+    pub fn bind_group(&self, pass_index: usize) -> &wgpu::BindGroup {
+        &self.bind_groups[self.parity][pass_index]
+    }
+
+    /// Compute runs after [`Self::finish_frame`], so the latest outputs
+    /// are the ones a first pass would see as the previous frame
+    pub fn compute_bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_groups[self.parity][0]
+    }
+
+    pub fn finish_frame(&mut self) {
+        self.parity = 1 - self.parity;
+    }
+}
@@ -0,0 +1,313 @@
+use std::cell::Cell;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use web_time::Instant;
+
+/// Compute dispatches past this many in a frame aren't timed on the GPU
+const MAX_COMPUTE_DISPATCHES: u32 = 256;
+const QUERY_COUNT: u32 = 4 + 2 * MAX_COMPUTE_DISPATCHES;
+const MAX_READBACKS: usize = 3;
+
+/// Where resolved queries of each pass go, offsets must be aligned for `resolve_query_set`
+const SHADER_OFFSET: u64 = 0;
+const UI_OFFSET: u64 = wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT;
+const COMPUTE_OFFSET: u64 = 2 * wgpu::QUERY_RESOLVE_BUFFER_ALIGNMENT;
+const RESOLVE_SIZE: u64 =
+    COMPUTE_OFFSET + 2 * MAX_COMPUTE_DISPATCHES as u64 * wgpu::QUERY_SIZE as u64;
+
+/// How [`PassTimings`] are measured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimingSource {
+    /// Timestamp queries, the time the GPU spent on each pass
+    Gpu,
+    /// The adapter doesn't support timestamp queries,
+    /// so this is the CPU time spent recording and submitting each pass
+    Cpu,
+}
+
+/// Time spent on each kind of pass in one frame, in milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PassTimings {
+    /// Every dispatch from [`crate::ControllerTrait::update`] summed up
+    pub compute: f32,
+    /// The passes from [`crate::ControllerTrait::describe_passes`] and the final shader pass
+    pub shader: f32,
+    pub ui: f32,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Pass {
+    Shader,
+    Ui,
+    #[cfg(feature = "compute")]
+    Compute,
+}
+
+/// Which queries were written in a frame
+#[derive(Clone, Copy, Default)]
+struct Written {
+    shader: bool,
+    ui: bool,
+    compute: u32,
+}
+
+pub(crate) struct Profiler {
+    gpu: Option<GpuTimer>,
+    written: Cell<Written>,
+    cpu_timings: Cell<PassTimings>,
+}
+
+impl Profiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let gpu = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| GpuTimer::new(device, queue));
+        Self {
+            gpu,
+            written: Default::default(),
+            cpu_timings: Default::default(),
+        }
+    }
+
+    pub fn source(&self) -> TimingSource {
+        match self.gpu {
+            Some(_) => TimingSource::Gpu,
+            None => TimingSource::Cpu,
+        }
+    }
+
+    /// Start timing a pass, call [`PassTimer::finish`] once it's submitted
+    pub fn start(&self, pass: Pass) -> PassTimer<'_> {
+        let mut written = self.written.get();
+        let query = match pass {
+            Pass::Shader => (!written.shader).then(|| {
+                written.shader = true;
+                0
+            }),
+            Pass::Ui => (!written.ui).then(|| {
+                written.ui = true;
+                2
+            }),
+            #[cfg(feature = "compute")]
+            Pass::Compute => (written.compute < MAX_COMPUTE_DISPATCHES).then(|| {
+                written.compute += 1;
+                2 + 2 * written.compute
+            }),
+        };
+        let query = match &self.gpu {
+            Some(gpu) => query.map(|index| {
+                self.written.set(written);
+                (&gpu.query_set, index)
+            }),
+            None => None,
+        };
+        PassTimer {
+            profiler: self,
+            pass,
+            query,
+            start: Instant::now(),
+        }
+    }
+
+    /// Read back the queries written since the last call
+    /// Returns the most recent timings that are available, which lag a few frames behind on the GPU
+    pub fn finish_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Option<PassTimings> {
+        let written = self.written.take();
+        match &mut self.gpu {
+            Some(gpu) => gpu.finish_frame(device, queue, written),
+            None => Some(self.cpu_timings.take()),
+        }
+    }
+}
+
+pub(crate) struct PassTimer<'a> {
+    profiler: &'a Profiler,
+    pass: Pass,
+    query: Option<(&'a wgpu::QuerySet, u32)>,
+    start: Instant,
+}
+
+impl<'a> PassTimer<'a> {
+    /// Timestamp writes for a render pass, when the timed section spans several render passes
+    /// only the first one should `begin` and only the last one should `end`
+    pub fn render_writes(
+        &self,
+        begin: bool,
+        end: bool,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'a>> {
+        let (query_set, index) = self.query.filter(|_| begin || end)?;
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: begin.then_some(index),
+            end_of_pass_write_index: end.then_some(index + 1),
+        })
+    }
+
+    #[cfg(feature = "compute")]
+    pub fn compute_writes(&self) -> Option<wgpu::ComputePassTimestampWrites<'a>> {
+        let (query_set, index) = self.query?;
+        Some(wgpu::ComputePassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    pub fn finish(self) {
+        if self.profiler.gpu.is_some() {
+            return;
+        }
+        let elapsed = self.start.elapsed().as_secs_f32() * 1000.0;
+        let mut timings = self.profiler.cpu_timings.get();
+        match self.pass {
+            Pass::Shader => timings.shader += elapsed,
+            Pass::Ui => timings.ui += elapsed,
+            #[cfg(feature = "compute")]
+            Pass::Compute => timings.compute += elapsed,
+        }
+        self.profiler.cpu_timings.set(timings);
+    }
+}
+
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readbacks: Vec<Readback>,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    frame: u64,
+}
+
+struct Readback {
+    buffer: wgpu::Buffer,
+    mapped: Arc<AtomicBool>,
+    /// The frame and queries being read back, `None` if the buffer is free
+    in_flight: Option<(u64, Written)>,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("Pass Timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: QUERY_COUNT,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Timestamp Resolve Buffer"),
+                size: RESOLVE_SIZE,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readbacks: vec![],
+            period: queue.get_timestamp_period(),
+            frame: 0,
+        }
+    }
+
+    fn finish_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        written: Written,
+    ) -> Option<PassTimings> {
+        device.poll(wgpu::Maintain::Poll);
+        let mut latest = None::<(u64, PassTimings)>;
+        for readback in &mut self.readbacks {
+            let Some((frame, written)) = readback.in_flight else {
+                continue;
+            };
+            if !readback.mapped.load(Ordering::Acquire) {
+                continue;
+            }
+            let timings = {
+                let view = readback.buffer.slice(..).get_mapped_range();
+                let timestamps: &[u64] = bytemuck::cast_slice(&view);
+                let duration = |offset: u64, pairs: u32| {
+                    let start = (offset / wgpu::QUERY_SIZE as u64) as usize;
+                    let ticks = timestamps[start..start + 2 * pairs as usize]
+                        .chunks_exact(2)
+                        .map(|pair| pair[1].saturating_sub(pair[0]))
+                        .sum::<u64>();
+                    ticks as f32 * self.period / 1_000_000.0
+                };
+                PassTimings {
+                    compute: duration(COMPUTE_OFFSET, written.compute),
+                    shader: duration(SHADER_OFFSET, written.shader as u32),
+                    ui: duration(UI_OFFSET, written.ui as u32),
+                }
+            };
+            readback.buffer.unmap();
+            readback.mapped.store(false, Ordering::Release);
+            readback.in_flight = None;
+            if latest.is_none_or(|(latest, _)| latest < frame) {
+                latest = Some((frame, timings));
+            }
+        }
+
+        if written.shader || written.ui || written.compute > 0 {
+            self.read_back(device, queue, written);
+        }
+        latest.map(|(_, timings)| timings)
+    }
+
+    fn read_back(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, written: Written) {
+        let index = match self.readbacks.iter().position(|r| r.in_flight.is_none()) {
+            Some(index) => index,
+            // All readbacks are still waiting on the GPU, skip this frame
+            None if self.readbacks.len() == MAX_READBACKS => return,
+            None => {
+                self.readbacks.push(Readback {
+                    buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Timestamp Readback Buffer"),
+                        size: RESOLVE_SIZE,
+                        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    }),
+                    mapped: Default::default(),
+                    in_flight: None,
+                });
+                self.readbacks.len() - 1
+            }
+        };
+        let readback = &mut self.readbacks[index];
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Timestamp Resolve Encoder"),
+        });
+        // Only written queries are resolved, the rest hold no timestamp
+        let mut resolve = |first: u32, pairs: u32, offset| {
+            if pairs > 0 {
+                encoder.resolve_query_set(
+                    &self.query_set,
+                    first..first + 2 * pairs,
+                    &self.resolve_buffer,
+                    offset,
+                );
+            }
+        };
+        resolve(0, written.shader as u32, SHADER_OFFSET);
+        resolve(2, written.ui as u32, UI_OFFSET);
+        resolve(4, written.compute, COMPUTE_OFFSET);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &readback.buffer, 0, RESOLVE_SIZE);
+        queue.submit(Some(encoder.finish()));
+
+        let mapped = readback.mapped.clone();
+        readback
+            .buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapped.store(true, Ordering::Release);
+                }
+            });
+        self.frame += 1;
+        readback.in_flight = Some((self.frame, written));
+    }
+}
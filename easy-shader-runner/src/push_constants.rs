@@ -0,0 +1,52 @@
+use crate::controller::ControllerTrait;
+use std::marker::PhantomData;
+
+/// Largest push constant struct, requested as `max_push_constant_size` and used for emulated ones
+pub(crate) const MAX_PUSH_CONSTANT_SIZE: u32 = 128;
+
+/// Byte sizes of [`ControllerTrait::PushConstants`] and [`ControllerTrait::ComputePushConstants`]
+#[derive(Clone, Copy)]
+pub(crate) struct PushConstantSizes {
+    pub fragment: u32,
+    #[cfg(feature = "compute")]
+    pub compute: u32,
+}
+
+impl PushConstantSizes {
+    /// Fails to compile for controllers with push constants that are too large or misaligned
+    pub fn of<C: ControllerTrait>() -> Self {
+        Self {
+            fragment: Size::<C::PushConstants>::CHECKED,
+            #[cfg(feature = "compute")]
+            compute: Size::<C::ComputePushConstants>::CHECKED,
+        }
+    }
+}
+
+struct Size<T>(PhantomData<T>);
+
+impl<T> Size<T> {
+    const CHECKED: u32 = {
+        let size = std::mem::size_of::<T>();
+        assert!(
+            size <= MAX_PUSH_CONSTANT_SIZE as usize,
+            "push constants can't be larger than 128 bytes"
+        );
+        assert!(
+            size % wgpu::PUSH_CONSTANT_ALIGNMENT as usize == 0,
+            "push constant size must be a multiple of 4 bytes"
+        );
+        size as u32
+    };
+}
+
+/// The range covering `size` bytes, none if push constants are empty or emulated
+pub(crate) fn push_constant_range(
+    stages: wgpu::ShaderStages,
+    size: u32,
+) -> Option<wgpu::PushConstantRange> {
+    (size > 0 && !cfg!(feature = "emulate_constants")).then_some(wgpu::PushConstantRange {
+        stages,
+        range: 0..size,
+    })
+}
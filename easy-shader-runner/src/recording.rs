@@ -0,0 +1,135 @@
+use crate::capture::Image;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+pub enum RecordingFormat {
+    /// `frame_000000.png`, `frame_000001.png`, ... inside the output directory
+    PngSequence,
+    /// Uncompressed 4:4:4 YUV in a single file, e.g. for `ffmpeg -i recording.y4m`
+    Y4m,
+}
+
+pub struct RecordingOptions {
+    pub path: PathBuf,
+    pub format: RecordingFormat,
+    /// Every rendered frame is treated as lasting exactly `1 / fps` seconds, at least 1
+    pub fps: u32,
+    pub include_ui: bool,
+}
+
+impl RecordingOptions {
+    pub fn png_sequence(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            path: directory.into(),
+            format: RecordingFormat::PngSequence,
+            fps: 60,
+            include_ui: false,
+        }
+    }
+
+    pub fn y4m(file: impl Into<PathBuf>) -> Self {
+        Self {
+            path: file.into(),
+            format: RecordingFormat::Y4m,
+            fps: 60,
+            include_ui: false,
+        }
+    }
+}
+
+pub(crate) struct Recorder {
+    options: RecordingOptions,
+    y4m: Option<BufWriter<File>>,
+    size: Option<glam::UVec2>,
+    frame: u32,
+}
+
+impl Recorder {
+    pub fn new(options: RecordingOptions) -> std::io::Result<Self> {
+        if options.fps == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "recording needs an fps of at least 1",
+            ));
+        }
+        let y4m = match options.format {
+            RecordingFormat::PngSequence => {
+                std::fs::create_dir_all(&options.path)?;
+                None
+            }
+            RecordingFormat::Y4m => Some(BufWriter::new(File::create(&options.path)?)),
+        };
+        Ok(Self {
+            options,
+            y4m,
+            size: None,
+            frame: 0,
+        })
+    }
+
+    pub fn include_ui(&self) -> bool {
+        self.options.include_ui
+    }
+
+    pub fn timestep(&self) -> f32 {
+        (self.options.fps as f32).recip()
+    }
+
+    pub fn write_frame(&mut self, image: &Image) -> Result<(), Box<dyn std::error::Error>> {
+        if *self.size.get_or_insert(image.size) != image.size {
+            return Err("the viewport was resized".into());
+        }
+        if let Some(writer) = &mut self.y4m {
+            if self.frame == 0 {
+                writeln!(
+                    writer,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                    image.size.x, image.size.y, self.options.fps
+                )?;
+            }
+            writeln!(writer, "FRAME")?;
+            writer.write_all(&rgba_to_yuv444(&image.data))?;
+        } else {
+            let path = self
+                .options
+                .path
+                .join(format!("frame_{:06}.png", self.frame));
+            image.save_png(path)?;
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) {
+        if let Some(writer) = &mut self.y4m {
+            if let Err(err) = writer.flush() {
+                log::error!("Failed to finish recording: {err}");
+                return;
+            }
+        }
+        log::info!(
+            "Recorded {} frames to {}",
+            self.frame,
+            self.options.path.display()
+        );
+    }
+}
+
+/// Planar BT.601 limited range, which is what decoders assume for y4m
+fn rgba_to_yuv444(rgba: &[u8]) -> Vec<u8> {
+    let pixels = rgba.len() / 4;
+    let mut yuv = vec![0; pixels * 3];
+    let (y_plane, uv) = yuv.split_at_mut(pixels);
+    let (u_plane, v_plane) = uv.split_at_mut(pixels);
+    for (i, pixel) in rgba.chunks_exact(4).enumerate() {
+        let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| c as f32);
+        let y = 16.0 + 0.257 * r + 0.504 * g + 0.098 * b;
+        let u = 128.0 - 0.148 * r - 0.291 * g + 0.439 * b;
+        let v = 128.0 + 0.439 * r - 0.368 * g - 0.071 * b;
+        y_plane[i] = y.round() as u8;
+        u_plane[i] = u.round() as u8;
+        v_plane[i] = v.round() as u8;
+    }
+    yuv
+}
@@ -0,0 +1,650 @@
+use crate::layout::BindingMismatch;
+use wgpu::naga;
+
+/// Returned when the shader module doesn't match what the controller described
+#[derive(Debug)]
+pub enum PipelineError {
+    MissingEntryPoint {
+        name: String,
+        stage: wgpu::ShaderStages,
+        /// Entry points of the same stage that are present in the module
+        available: Vec<String>,
+    },
+    /// Described bindings that don't match the shader
+    BindingMismatches(Vec<BindingMismatch>),
+    /// wgpu rejected the shader module or a pipeline
+    Validation(wgpu::Error),
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingEntryPoint {
+                name,
+                stage,
+                available,
+            } => {
+                let stage = stage_name(*stage);
+                write!(f, "shader module has no {stage} entry point `{name}`")?;
+                if available.is_empty() {
+                    write!(f, ", it has no {stage} entry points at all")
+                } else {
+                    write!(f, ", available: `{}`", available.join("`, `"))
+                }
+            }
+            Self::BindingMismatches(mismatches) => {
+                write!(f, "described bindings don't match the shader module")?;
+                for mismatch in mismatches {
+                    write!(f, "\n{mismatch}")?;
+                }
+                Ok(())
+            }
+            Self::Validation(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+pub(crate) fn stage_name(stage: wgpu::ShaderStages) -> &'static str {
+    match stage {
+        wgpu::ShaderStages::VERTEX => "vertex",
+        wgpu::ShaderStages::FRAGMENT => "fragment",
+        wgpu::ShaderStages::COMPUTE => "compute",
+        _ => "shader",
+    }
+}
+
+pub(crate) struct EntryPoint {
+    pub name: String,
+    pub stage: wgpu::ShaderStages,
+    /// Resources the entry point and the functions it calls access
+    pub bindings: Vec<ReflectedBinding>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ReflectedBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub name: Option<String>,
+    pub kind: ReflectedKind,
+    pub stages: wgpu::ShaderStages,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ReflectedKind {
+    StorageBuffer {
+        read_only: bool,
+    },
+    UniformBuffer,
+    Texture {
+        view_dimension: wgpu::TextureViewDimension,
+        multisampled: bool,
+    },
+    StorageTexture {
+        view_dimension: wgpu::TextureViewDimension,
+        format: wgpu::TextureFormat,
+        /// `None` when the shader neither forbids reads nor writes
+        access: Option<wgpu::StorageTextureAccess>,
+    },
+    Sampler,
+    /// Combined image samplers, texel buffers and such
+    Unsupported,
+}
+
+impl ReflectedKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::StorageBuffer { .. } => "storage buffer",
+            Self::UniformBuffer => "uniform buffer",
+            Self::Texture { .. } => "texture",
+            Self::StorageTexture { .. } => "storage texture",
+            Self::Sampler => "sampler",
+            Self::Unsupported => "unsupported binding",
+        }
+    }
+
+    /// Combine the kinds of variables sharing a binding in different entry points
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::StorageBuffer { read_only: a }, Self::StorageBuffer { read_only: b }) => {
+                Self::StorageBuffer { read_only: a && b }
+            }
+            (
+                Self::StorageTexture {
+                    view_dimension,
+                    format,
+                    access: a,
+                },
+                Self::StorageTexture { access: b, .. },
+            ) => Self::StorageTexture {
+                view_dimension,
+                format,
+                access: if a == b { a } else { None },
+            },
+            (kind, _) => kind,
+        }
+    }
+}
+
+/// The entry points declared in a SPIR-V module and the bindings they use
+/// Returns `None` if naga can't parse or validate the module, wgpu reports why when creating it
+pub(crate) fn entry_points(shader_bytes: &[u8]) -> Option<Vec<EntryPoint>> {
+    let module = naga::front::spv::parse_u8_slice(shader_bytes, &Default::default()).ok()?;
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .ok()?;
+    let entry_points = module
+        .entry_points
+        .iter()
+        .enumerate()
+        .map(|(index, entry_point)| {
+            let stage = match entry_point.stage {
+                naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+            };
+            // Includes globals used by the functions the entry point calls
+            let uses = info.get_entry_point(index);
+            let mut bindings = module
+                .global_variables
+                .iter()
+                .filter(|&(handle, _)| !uses[handle].is_empty())
+                .filter_map(|(_, variable)| {
+                    let binding = variable.binding.as_ref()?;
+                    Some(ReflectedBinding {
+                        set: binding.group,
+                        binding: binding.binding,
+                        name: variable.name.clone(),
+                        kind: binding_kind(&module, variable),
+                        stages: stage,
+                    })
+                })
+                .collect::<Vec<_>>();
+            bindings.sort_by_key(|b| (b.set, b.binding));
+            EntryPoint {
+                name: entry_point.name.clone(),
+                stage,
+                bindings,
+            }
+        })
+        .collect();
+    Some(entry_points)
+}
+
+fn binding_kind(module: &naga::Module, variable: &naga::GlobalVariable) -> ReflectedKind {
+    let mut ty = variable.ty;
+    // Binding arrays are described one element at a time
+    while let naga::TypeInner::BindingArray { base, .. } = module.types[ty].inner {
+        ty = base;
+    }
+    match (variable.space, &module.types[ty].inner) {
+        (naga::AddressSpace::Uniform, _) => ReflectedKind::UniformBuffer,
+        (naga::AddressSpace::Storage { access }, _) => ReflectedKind::StorageBuffer {
+            read_only: !access.contains(naga::StorageAccess::STORE),
+        },
+        (
+            naga::AddressSpace::Handle,
+            &naga::TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            },
+        ) => image_kind(dim, arrayed, class),
+        (naga::AddressSpace::Handle, naga::TypeInner::Sampler { .. }) => ReflectedKind::Sampler,
+        _ => ReflectedKind::Unsupported,
+    }
+}
+
+fn image_kind(dim: naga::ImageDimension, arrayed: bool, class: naga::ImageClass) -> ReflectedKind {
+    let view_dimension = match (dim, arrayed) {
+        (naga::ImageDimension::D1, false) => wgpu::TextureViewDimension::D1,
+        (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (naga::ImageDimension::D3, false) => wgpu::TextureViewDimension::D3,
+        (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+        _ => return ReflectedKind::Unsupported,
+    };
+    match class {
+        naga::ImageClass::Storage { format, access } => ReflectedKind::StorageTexture {
+            view_dimension,
+            format: storage_format(format),
+            access: match (
+                access.contains(naga::StorageAccess::LOAD),
+                access.contains(naga::StorageAccess::STORE),
+            ) {
+                (true, false) => Some(wgpu::StorageTextureAccess::ReadOnly),
+                (false, true) => Some(wgpu::StorageTextureAccess::WriteOnly),
+                _ => None,
+            },
+        },
+        naga::ImageClass::Sampled { multi, .. } | naga::ImageClass::Depth { multi } => {
+            ReflectedKind::Texture {
+                view_dimension,
+                multisampled: multi,
+            }
+        }
+    }
+}
+
+/// Check that `name` is an entry point of `stage`
+pub(crate) fn check_entry_point(
+    entry_points: &[EntryPoint],
+    name: &str,
+    stage: wgpu::ShaderStages,
+) -> Result<(), PipelineError> {
+    let of_stage = entry_points.iter().filter(|e| e.stage == stage);
+    if of_stage.clone().any(|e| e.name == name) {
+        return Ok(());
+    }
+    Err(PipelineError::MissingEntryPoint {
+        name: name.to_owned(),
+        stage,
+        available: of_stage.map(|e| e.name.clone()).collect(),
+    })
+}
+
+fn storage_format(format: naga::StorageFormat) -> wgpu::TextureFormat {
+    use naga::StorageFormat as Sf;
+    use wgpu::TextureFormat as Tf;
+    match format {
+        Sf::R8Unorm => Tf::R8Unorm,
+        Sf::R8Snorm => Tf::R8Snorm,
+        Sf::R8Uint => Tf::R8Uint,
+        Sf::R8Sint => Tf::R8Sint,
+        Sf::R16Uint => Tf::R16Uint,
+        Sf::R16Sint => Tf::R16Sint,
+        Sf::R16Float => Tf::R16Float,
+        Sf::Rg8Unorm => Tf::Rg8Unorm,
+        Sf::Rg8Snorm => Tf::Rg8Snorm,
+        Sf::Rg8Uint => Tf::Rg8Uint,
+        Sf::Rg8Sint => Tf::Rg8Sint,
+        Sf::R32Uint => Tf::R32Uint,
+        Sf::R32Sint => Tf::R32Sint,
+        Sf::R32Float => Tf::R32Float,
+        Sf::Rg16Uint => Tf::Rg16Uint,
+        Sf::Rg16Sint => Tf::Rg16Sint,
+        Sf::Rg16Float => Tf::Rg16Float,
+        Sf::Rgba8Unorm => Tf::Rgba8Unorm,
+        Sf::Rgba8Snorm => Tf::Rgba8Snorm,
+        Sf::Rgba8Uint => Tf::Rgba8Uint,
+        Sf::Rgba8Sint => Tf::Rgba8Sint,
+        Sf::Bgra8Unorm => Tf::Bgra8Unorm,
+        Sf::Rgb10a2Uint => Tf::Rgb10a2Uint,
+        Sf::Rgb10a2Unorm => Tf::Rgb10a2Unorm,
+        Sf::Rg11b10Ufloat => Tf::Rg11b10Ufloat,
+        Sf::Rg32Uint => Tf::Rg32Uint,
+        Sf::Rg32Sint => Tf::Rg32Sint,
+        Sf::Rg32Float => Tf::Rg32Float,
+        Sf::Rgba16Uint => Tf::Rgba16Uint,
+        Sf::Rgba16Sint => Tf::Rgba16Sint,
+        Sf::Rgba16Float => Tf::Rgba16Float,
+        Sf::Rgba32Uint => Tf::Rgba32Uint,
+        Sf::Rgba32Sint => Tf::Rgba32Sint,
+        Sf::Rgba32Float => Tf::Rgba32Float,
+        Sf::R16Unorm => Tf::R16Unorm,
+        Sf::R16Snorm => Tf::R16Snorm,
+        Sf::Rg16Unorm => Tf::Rg16Unorm,
+        Sf::Rg16Snorm => Tf::Rg16Snorm,
+        Sf::Rgba16Unorm => Tf::Rgba16Unorm,
+        Sf::Rgba16Snorm => Tf::Rgba16Snorm,
+    }
+}
+
+pub(crate) fn used_bindings(entry_points: &[EntryPoint], names: &[&str]) -> Vec<ReflectedBinding> {
+    let mut used = Vec::<ReflectedBinding>::new();
+    for binding in entry_points
+        .iter()
+        .filter(|e| names.contains(&e.name.as_str()))
+        .flat_map(|e| &e.bindings)
+    {
+        match used
+            .iter_mut()
+            .find(|b| (b.set, b.binding) == (binding.set, binding.binding))
+        {
+            Some(b) => {
+                b.stages |= binding.stages;
+                b.kind = b.kind.merge(binding.kind);
+            }
+            None => used.push(binding.clone()),
+        }
+    }
+    used
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAGIC: u32 = 0x0723_0203;
+
+    const OP_NAME: u32 = 5;
+    const OP_MEMORY_MODEL: u32 = 14;
+    const OP_ENTRY_POINT: u32 = 15;
+    const OP_EXECUTION_MODE: u32 = 16;
+    const OP_CAPABILITY: u32 = 17;
+    const OP_TYPE_VOID: u32 = 19;
+    const OP_TYPE_INT: u32 = 21;
+    const OP_TYPE_FLOAT: u32 = 22;
+    const OP_TYPE_VECTOR: u32 = 23;
+    const OP_TYPE_IMAGE: u32 = 25;
+    const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+    const OP_TYPE_STRUCT: u32 = 30;
+    const OP_TYPE_POINTER: u32 = 32;
+    const OP_TYPE_FUNCTION: u32 = 33;
+    const OP_CONSTANT: u32 = 43;
+    const OP_CONSTANT_COMPOSITE: u32 = 44;
+    const OP_FUNCTION: u32 = 54;
+    const OP_FUNCTION_END: u32 = 56;
+    const OP_FUNCTION_CALL: u32 = 57;
+    const OP_VARIABLE: u32 = 59;
+    const OP_LOAD: u32 = 61;
+    const OP_ACCESS_CHAIN: u32 = 65;
+    const OP_IMAGE_WRITE: u32 = 99;
+    const OP_DECORATE: u32 = 71;
+    const OP_MEMBER_DECORATE: u32 = 72;
+    const OP_LABEL: u32 = 248;
+    const OP_RETURN: u32 = 253;
+
+    const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+    const EXECUTION_MODEL_GL_COMPUTE: u32 = 5;
+    const EXECUTION_MODE_ORIGIN_UPPER_LEFT: u32 = 7;
+    const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+    const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+    const STORAGE_CLASS_UNIFORM: u32 = 2;
+    const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+    const DECORATION_BLOCK: u32 = 2;
+    const DECORATION_BUFFER_BLOCK: u32 = 3;
+    const DECORATION_ARRAY_STRIDE: u32 = 6;
+    const DECORATION_NON_WRITABLE: u32 = 24;
+    const DECORATION_NON_READABLE: u32 = 25;
+    const DECORATION_BINDING: u32 = 33;
+    const DECORATION_DESCRIPTOR_SET: u32 = 34;
+    const DECORATION_OFFSET: u32 = 35;
+
+    const VOID: u32 = 1;
+    const FUNCTION_TYPE: u32 = 2;
+    const UINT: u32 = 3;
+    const ARRAY: u32 = 4;
+    const BLOCK: u32 = 5;
+    const POINTER: u32 = 6;
+    const VARIABLE: u32 = 7;
+    const MAIN: u32 = 8;
+    const HELPER: u32 = 9;
+    const ELEMENT_POINTER: u32 = 10;
+    const ELEMENT: u32 = 11;
+    const ZERO: u32 = 12;
+    const FLOAT: u32 = 13;
+    const IMAGE: u32 = 14;
+    const MAIN_LABEL: u32 = 15;
+    const HELPER_LABEL: u32 = 16;
+    const CALL: u32 = 17;
+    const VALUE: u32 = 18;
+    const INT: u32 = 19;
+    const INT_ZERO: u32 = 20;
+    const IVEC2: u32 = 21;
+    const COORDINATE: u32 = 22;
+    const FLOAT_ZERO: u32 = 23;
+    const VEC4: u32 = 24;
+    const TEXEL: u32 = 25;
+
+    fn string(s: &str) -> Vec<u32> {
+        let mut bytes = s.as_bytes().to_vec();
+        bytes.resize(s.len() / 4 * 4 + 4, 0);
+        bytes
+            .chunks_exact(4)
+            .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+            .collect()
+    }
+
+    /// Assemble a module from opcodes and their operands
+    fn assemble(instructions: &[(u32, Vec<u32>)]) -> Vec<u8> {
+        let mut words = vec![MAGIC, 0x0001_0300, 0, 100, 0];
+        for (opcode, operands) in instructions {
+            words.push((operands.len() as u32 + 1) << 16 | opcode);
+            words.extend(operands);
+        }
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    /// The instructions every module starts with, declaring `MAIN` as `name`
+    fn preamble(execution_model: u32, name: &str) -> Vec<(u32, Vec<u32>)> {
+        let execution_mode = match execution_model {
+            EXECUTION_MODEL_GL_COMPUTE => vec![MAIN, EXECUTION_MODE_LOCAL_SIZE, 1, 1, 1],
+            _ => vec![MAIN, EXECUTION_MODE_ORIGIN_UPPER_LEFT],
+        };
+        vec![
+            (OP_CAPABILITY, vec![1]),
+            (OP_MEMORY_MODEL, vec![0, 1]),
+            (
+                OP_ENTRY_POINT,
+                [vec![execution_model, MAIN], string(name)].concat(),
+            ),
+            (OP_EXECUTION_MODE, execution_mode),
+        ]
+    }
+
+    /// A storage buffer of `u32`s at set 0, binding 1 that `MAIN` reads through a helper function,
+    /// unless `call_helper` is false
+    /// In the `Uniform` storage class it is declared the SPIR-V 1.0 way, with a `BufferBlock`
+    fn storage_buffer_module(
+        execution_model: u32,
+        name: &str,
+        storage_class: u32,
+        decorations: &[(u32, Vec<u32>)],
+        call_helper: bool,
+    ) -> Vec<u8> {
+        let block_decoration = match storage_class {
+            STORAGE_CLASS_UNIFORM => DECORATION_BUFFER_BLOCK,
+            _ => DECORATION_BLOCK,
+        };
+        let mut instructions = preamble(execution_model, name);
+        instructions.extend([
+            (OP_NAME, [vec![VARIABLE], string("cells")].concat()),
+            (OP_DECORATE, vec![ARRAY, DECORATION_ARRAY_STRIDE, 4]),
+            (OP_MEMBER_DECORATE, vec![BLOCK, 0, DECORATION_OFFSET, 0]),
+            (OP_DECORATE, vec![BLOCK, block_decoration]),
+            (OP_DECORATE, vec![VARIABLE, DECORATION_DESCRIPTOR_SET, 0]),
+            (OP_DECORATE, vec![VARIABLE, DECORATION_BINDING, 1]),
+        ]);
+        instructions.extend_from_slice(decorations);
+        instructions.extend([
+            (OP_TYPE_VOID, vec![VOID]),
+            (OP_TYPE_FUNCTION, vec![FUNCTION_TYPE, VOID]),
+            (OP_TYPE_INT, vec![UINT, 32, 0]),
+            (OP_CONSTANT, vec![UINT, ZERO, 0]),
+            (OP_TYPE_RUNTIME_ARRAY, vec![ARRAY, UINT]),
+            (OP_TYPE_STRUCT, vec![BLOCK, ARRAY]),
+            (OP_TYPE_POINTER, vec![POINTER, storage_class, BLOCK]),
+            (OP_TYPE_POINTER, vec![ELEMENT_POINTER, storage_class, UINT]),
+            (OP_VARIABLE, vec![POINTER, VARIABLE, storage_class]),
+            (OP_FUNCTION, vec![VOID, HELPER, 0, FUNCTION_TYPE]),
+            (OP_LABEL, vec![HELPER_LABEL]),
+            (
+                OP_ACCESS_CHAIN,
+                vec![ELEMENT_POINTER, ELEMENT, VARIABLE, ZERO, ZERO],
+            ),
+            (OP_LOAD, vec![UINT, VALUE, ELEMENT]),
+            (OP_RETURN, vec![]),
+            (OP_FUNCTION_END, vec![]),
+            (OP_FUNCTION, vec![VOID, MAIN, 0, FUNCTION_TYPE]),
+            (OP_LABEL, vec![MAIN_LABEL]),
+        ]);
+        if call_helper {
+            instructions.push((OP_FUNCTION_CALL, vec![VOID, CALL, HELPER]));
+        }
+        instructions.extend([(OP_RETURN, vec![]), (OP_FUNCTION_END, vec![])]);
+        assemble(&instructions)
+    }
+
+    fn compute_storage_buffer_module(decorations: &[(u32, Vec<u32>)]) -> Vec<u8> {
+        storage_buffer_module(
+            EXECUTION_MODEL_GL_COMPUTE,
+            "main_cs",
+            STORAGE_CLASS_STORAGE_BUFFER,
+            decorations,
+            true,
+        )
+    }
+
+    fn only_binding(bytes: &[u8]) -> ReflectedBinding {
+        let entry_points = entry_points(bytes).expect("valid SPIR-V");
+        assert_eq!(entry_points.len(), 1);
+        assert_eq!(entry_points[0].bindings.len(), 1);
+        entry_points[0].bindings[0].clone()
+    }
+
+    #[test]
+    fn writable_storage_buffer_used_through_a_call() {
+        let entry_points = entry_points(&compute_storage_buffer_module(&[])).unwrap();
+        assert_eq!(entry_points[0].name, "main_cs");
+        assert_eq!(entry_points[0].stage, wgpu::ShaderStages::COMPUTE);
+        let binding = only_binding(&compute_storage_buffer_module(&[]));
+        assert_eq!((binding.set, binding.binding), (0, 1));
+        assert_eq!(binding.name.as_deref(), Some("cells"));
+        assert_eq!(binding.stages, wgpu::ShaderStages::COMPUTE);
+        assert_eq!(
+            binding.kind,
+            ReflectedKind::StorageBuffer { read_only: false }
+        );
+    }
+
+    #[test]
+    fn buffer_block_with_non_writable_members() {
+        let module = storage_buffer_module(
+            EXECUTION_MODEL_GL_COMPUTE,
+            "main_cs",
+            STORAGE_CLASS_UNIFORM,
+            &[(OP_MEMBER_DECORATE, vec![BLOCK, 0, DECORATION_NON_WRITABLE])],
+            true,
+        );
+        let binding = only_binding(&module);
+        assert_eq!(
+            binding.kind,
+            ReflectedKind::StorageBuffer { read_only: true }
+        );
+    }
+
+    #[test]
+    fn storage_buffer_with_non_writable_variable() {
+        let module = compute_storage_buffer_module(&[(
+            OP_DECORATE,
+            vec![VARIABLE, DECORATION_NON_WRITABLE],
+        )]);
+        let binding = only_binding(&module);
+        assert_eq!(
+            binding.kind,
+            ReflectedKind::StorageBuffer { read_only: true }
+        );
+    }
+
+    #[test]
+    fn unused_binding_is_left_out() {
+        let module = storage_buffer_module(
+            EXECUTION_MODEL_FRAGMENT,
+            "main_fs",
+            STORAGE_CLASS_STORAGE_BUFFER,
+            &[],
+            false,
+        );
+        let entry_points = entry_points(&module).expect("valid SPIR-V");
+        assert_eq!(entry_points[0].stage, wgpu::ShaderStages::FRAGMENT);
+        assert!(entry_points[0].bindings.is_empty());
+    }
+
+    #[test]
+    fn write_only_storage_texture() {
+        let mut instructions = preamble(EXECUTION_MODEL_GL_COMPUTE, "main_cs");
+        instructions.extend([
+            (OP_DECORATE, vec![VARIABLE, DECORATION_DESCRIPTOR_SET, 0]),
+            (OP_DECORATE, vec![VARIABLE, DECORATION_BINDING, 2]),
+            (OP_DECORATE, vec![VARIABLE, DECORATION_NON_READABLE]),
+            (OP_TYPE_VOID, vec![VOID]),
+            (OP_TYPE_FUNCTION, vec![FUNCTION_TYPE, VOID]),
+            (OP_TYPE_FLOAT, vec![FLOAT, 32]),
+            (OP_TYPE_INT, vec![INT, 32, 1]),
+            (OP_TYPE_VECTOR, vec![IVEC2, INT, 2]),
+            (OP_TYPE_VECTOR, vec![VEC4, FLOAT, 4]),
+            (OP_CONSTANT, vec![INT, INT_ZERO, 0]),
+            (OP_CONSTANT, vec![FLOAT, FLOAT_ZERO, 0]),
+            (
+                OP_CONSTANT_COMPOSITE,
+                vec![IVEC2, COORDINATE, INT_ZERO, INT_ZERO],
+            ),
+            (
+                OP_CONSTANT_COMPOSITE,
+                vec![VEC4, TEXEL, FLOAT_ZERO, FLOAT_ZERO, FLOAT_ZERO, FLOAT_ZERO],
+            ),
+            // 2D, not depth, arrayed or multisampled, used without a sampler, R32f
+            (OP_TYPE_IMAGE, vec![IMAGE, FLOAT, 1, 0, 0, 0, 2, 3]),
+            (
+                OP_TYPE_POINTER,
+                vec![POINTER, STORAGE_CLASS_UNIFORM_CONSTANT, IMAGE],
+            ),
+            (
+                OP_VARIABLE,
+                vec![POINTER, VARIABLE, STORAGE_CLASS_UNIFORM_CONSTANT],
+            ),
+            (OP_FUNCTION, vec![VOID, MAIN, 0, FUNCTION_TYPE]),
+            (OP_LABEL, vec![MAIN_LABEL]),
+            (OP_LOAD, vec![IMAGE, VALUE, VARIABLE]),
+            (OP_IMAGE_WRITE, vec![VALUE, COORDINATE, TEXEL]),
+            (OP_RETURN, vec![]),
+            (OP_FUNCTION_END, vec![]),
+        ]);
+        let binding = only_binding(&assemble(&instructions));
+        assert_eq!((binding.set, binding.binding), (0, 2));
+        assert_eq!(
+            binding.kind,
+            ReflectedKind::StorageTexture {
+                view_dimension: wgpu::TextureViewDimension::D2,
+                format: wgpu::TextureFormat::R32Float,
+                access: Some(wgpu::StorageTextureAccess::WriteOnly),
+            }
+        );
+    }
+
+    #[test]
+    fn not_spirv() {
+        assert!(entry_points(b"not a shader module!").is_none());
+        assert!(entry_points(&[0; 7]).is_none());
+    }
+
+    #[test]
+    fn missing_entry_point_lists_the_same_stage() {
+        let entry_points = entry_points(&compute_storage_buffer_module(&[])).unwrap();
+        let Err(PipelineError::MissingEntryPoint { available, .. }) =
+            check_entry_point(&entry_points, "main_cs2", wgpu::ShaderStages::COMPUTE)
+        else {
+            panic!("main_cs2 isn't in the module");
+        };
+        assert_eq!(available, ["main_cs"]);
+        assert!(check_entry_point(&entry_points, "main_cs", wgpu::ShaderStages::COMPUTE).is_ok());
+    }
+
+    #[test]
+    fn used_bindings_merge_stages() {
+        let mut entry_points = entry_points(&compute_storage_buffer_module(&[])).unwrap();
+        entry_points.extend(
+            super::entry_points(&storage_buffer_module(
+                EXECUTION_MODEL_FRAGMENT,
+                "main_fs",
+                STORAGE_CLASS_STORAGE_BUFFER,
+                &[],
+                true,
+            ))
+            .unwrap(),
+        );
+        let used = used_bindings(&entry_points, &["main_cs", "main_fs"]);
+        assert_eq!(used.len(), 1);
+        assert_eq!(
+            used[0].stages,
+            wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT
+        );
+        assert!(used_bindings(&entry_points, &["main_vs"]).is_empty());
+    }
+}
@@ -1,16 +1,30 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::capture::read_viewport;
+#[cfg(feature = "compute")]
+use crate::controller::ComputeEntry;
 use crate::{
-    bind_group_buffer::BufferDescriptor,
+    bind_group_buffer::{BoundResource, BufferDescriptor, ResourceKey},
+    clock::FrameTime,
     context::GraphicsContext,
-    controller::ControllerTrait,
+    controller::{ControllerTrait, EntryPoints},
+    layout::{layout_entries, LayoutSource},
+    passes::{PassDescriptor, PassTargets},
+    profiler::{Pass, Profiler},
+    push_constants::{push_constant_range, PushConstantSizes},
+    reflection::{self, EntryPoint, PipelineError, ReflectedBinding},
+    shader_modules::ShaderModules,
     ui::{Ui, UiState},
 };
 use egui_winit::winit::window::Window;
+use std::sync::Arc;
+#[cfg(feature = "emulate_constants")]
 use wgpu::util::DeviceExt;
 
 struct Pipelines {
     render: wgpu::RenderPipeline,
+    passes: Vec<wgpu::RenderPipeline>,
     #[cfg(feature = "compute")]
-    compute: wgpu::ComputePipeline,
+    compute: Vec<wgpu::ComputePipeline>,
 }
 
 struct PipelineLayouts {
@@ -22,46 +36,130 @@ struct PipelineLayouts {
 struct BindGroupData {
     #[cfg(feature = "emulate_constants")]
     buffers: Vec<wgpu::Buffer>,
+    /// The described resources and what they were created from, empty for emulated push constants
+    resources: Vec<(ResourceKey, BoundResource)>,
     bind_group: wgpu::BindGroup,
 }
 
 pub struct RenderPass {
     pipelines: Pipelines,
     #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
-    pipeline_layouts: PipelineLayouts,
+    entry_points: EntryPoints,
+    #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+    passes: Vec<PassDescriptor>,
+    pass_targets: Option<PassTargets>,
+    #[cfg(feature = "compute")]
+    compute_entry_points: Vec<&'static str>,
     ui_renderer: egui_wgpu::Renderer,
+    profiler: Profiler,
+    layout_source: LayoutSource,
+    #[cfg(any(
+        feature = "emulate_constants",
+        all(feature = "hot-reload-shader", not(target_arch = "wasm32"))
+    ))]
+    push_constant_sizes: PushConstantSizes,
+    /// Bindings used by the active entry points, `None` if the module couldn't be reflected
+    reflected_bindings: Option<Vec<ReflectedBinding>>,
+    /// Entries of the described sets, the layouts also include emulated push constants
+    layout_entries: Vec<Vec<wgpu::BindGroupLayoutEntry>>,
+    bind_group_layouts: Vec<wgpu::BindGroupLayout>,
     bind_group_data: Vec<BindGroupData>,
     shader_viewport: egui::Rect,
+    clear_color: wgpu::Color,
 }
 
 impl RenderPass {
     pub fn new<C: ControllerTrait>(
         ctx: &GraphicsContext,
-        shader_bytes: &[u8],
+        shader: &ShaderModules,
         controller: &mut C,
+        clear_color: wgpu::Color,
     ) -> Self {
         let buffer_data = &controller.describe_buffers();
-        let bind_group_layouts = create_bind_group_layouts(ctx, buffer_data);
-        let pipeline_layouts = create_pipeline_layouts(ctx, &bind_group_layouts);
+        let entry_points = controller.describe_entry_points();
+        let passes = controller.describe_passes();
+        #[cfg(feature = "compute")]
+        let compute_entry_points = controller.describe_compute_entry_points();
+        let layout_source = controller.layout_source();
+        let push_constant_sizes = PushConstantSizes::of::<C>();
+        let pass_targets = (!passes.is_empty()).then(|| PassTargets::new(&ctx.device, &passes));
+        let reflected_bindings = shader
+            .entry_points()
+            .map(|reflected| {
+                active_bindings(
+                    &reflected,
+                    &entry_points,
+                    &passes,
+                    #[cfg(feature = "compute")]
+                    &compute_entry_points,
+                )
+            })
+            .transpose()
+            .unwrap_or_else(|err| panic!("{err}"));
+        let layout_entries = layout_entries(
+            buffer_data,
+            reflected_bindings.as_deref(),
+            layout_source,
+            reserved_sets(pass_targets.as_ref()),
+        )
+        .map_err(PipelineError::BindingMismatches)
+        .unwrap_or_else(|err| panic!("{err}"));
+        let bind_group_layouts = create_bind_group_layouts(ctx, &layout_entries);
+        let pipeline_layouts = create_pipeline_layouts(
+            ctx,
+            &bind_group_layouts,
+            pass_targets.as_ref(),
+            push_constant_sizes,
+        );
         let pipelines = create_pipelines(
             &ctx.device,
             &pipeline_layouts,
             ctx.config.format,
-            shader_bytes,
+            &entry_points,
+            &passes,
+            #[cfg(feature = "compute")]
+            &compute_entry_points,
+            shader,
+        )
+        .unwrap_or_else(|err| panic!("{err}"));
+        let (bind_group_data, writable_buffers, writable_textures) = create_bind_groups(
+            ctx,
+            buffer_data,
+            &bind_group_layouts,
+            #[cfg(feature = "emulate_constants")]
+            push_constant_sizes,
+            &[],
+            &[],
         );
-        let (bind_group_data, writable_buffers) =
-            create_bind_groups(ctx, buffer_data, &bind_group_layouts);
         controller.receive_buffers(writable_buffers);
+        controller.receive_textures(writable_textures);
 
         let ui_renderer = egui_wgpu::Renderer::new(&ctx.device, ctx.config.format, None, 1, false);
+        let profiler = Profiler::new(&ctx.device, &ctx.queue);
 
         Self {
             pipelines,
             #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
-            pipeline_layouts,
+            entry_points,
+            #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+            passes,
+            pass_targets,
+            #[cfg(feature = "compute")]
+            compute_entry_points,
             ui_renderer,
+            profiler,
+            layout_source,
+            #[cfg(any(
+                feature = "emulate_constants",
+                all(feature = "hot-reload-shader", not(target_arch = "wasm32"))
+            ))]
+            push_constant_sizes,
+            reflected_bindings,
+            layout_entries,
+            bind_group_layouts,
             bind_group_data,
             shader_viewport: egui::Rect::NAN,
+            clear_color,
         }
     }
 
@@ -69,24 +167,29 @@ impl RenderPass {
     pub fn compute(
         &self,
         ctx: &GraphicsContext,
+        entry: ComputeEntry,
         dimensions: glam::UVec3,
         threads: glam::UVec3,
         push_constants: &[u8],
     ) {
+        let index = entry.index(&self.compute_entry_points);
         let workspace = (dimensions.as_vec3() / threads.as_vec3()).ceil().as_uvec3();
         let mut encoder = ctx
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let timer = self.profiler.start(Pass::Compute);
         {
             let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
                 label: None,
-                timestamp_writes: None,
+                timestamp_writes: timer.compute_writes(),
             });
 
-            cpass.set_pipeline(&self.pipelines.compute);
+            cpass.set_pipeline(&self.pipelines.compute[index]);
             {
                 #[cfg(not(feature = "emulate_constants"))]
-                cpass.set_push_constants(0, push_constants);
+                if !push_constants.is_empty() {
+                    cpass.set_push_constants(0, push_constants);
+                }
                 #[cfg(feature = "emulate_constants")]
                 ctx.queue.write_buffer(
                     &self.bind_group_data.last().unwrap().buffers[1],
@@ -94,12 +197,17 @@ impl RenderPass {
                     push_constants,
                 );
             }
-            for (i, bind_group_data) in self.bind_group_data.iter().enumerate() {
-                cpass.set_bind_group(i as u32, &bind_group_data.bind_group, &[]);
+            let pass_bind_group = self
+                .pass_targets
+                .as_ref()
+                .map(PassTargets::compute_bind_group);
+            for (i, bind_group) in self.bind_groups(pass_bind_group).enumerate() {
+                cpass.set_bind_group(i as u32, bind_group, &[]);
             }
             cpass.dispatch_workgroups(workspace.x, workspace.y, workspace.z);
         }
         ctx.queue.submit(Some(encoder.finish()));
+        timer.finish();
     }
 
     pub fn render<C: ControllerTrait>(
@@ -110,101 +218,191 @@ impl RenderPass {
         ui_state: &mut UiState,
         controller: &mut C,
     ) -> Result<(), wgpu::SurfaceError> {
-        let output = match ctx.surface.get_current_texture() {
+        let surface = ctx
+            .surface()
+            .expect("rendering to a window requires a surface");
+        let output = match surface.get_current_texture() {
             Ok(surface_texture) => surface_texture,
             Err(err) => {
                 eprintln!("get_current_texture error: {err:?}");
                 return match err {
                     wgpu::SurfaceError::Lost => {
-                        ctx.surface.configure(&ctx.device, &ctx.config);
+                        ctx.configure_surface();
                         Ok(())
                     }
                     _ => Err(err),
                 };
             }
         };
-        let output_view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
-        self.render_ui(ctx, &output_view, window, ui, ui_state, controller);
+        self.render_ui(ctx, &output.texture, window, ui, ui_state, controller);
+        // Compute dispatched since the last frame is part of this one, since it's what this frame shows
+        if let Some(timings) = self.profiler.finish_frame(&ctx.device, &ctx.queue) {
+            ui_state.push_timings(timings, self.profiler.source());
+        }
 
         output.present();
 
         Ok(())
     }
 
+    /// Render only the shader, filling `output_view`
+    pub fn render_offscreen<C: ControllerTrait>(
+        &mut self,
+        ctx: &GraphicsContext,
+        output_view: &wgpu::TextureView,
+        controller: &mut C,
+        time: FrameTime,
+    ) {
+        let size = egui::vec2(ctx.config.width as f32, ctx.config.height as f32);
+        self.render_shader(
+            ctx,
+            output_view,
+            controller,
+            egui::Rect::from_min_size(egui::Pos2::ZERO, size),
+            time,
+        );
+        // Nowhere to show them, but queries still have to be read back to be reused
+        self.profiler.finish_frame(&ctx.device, &ctx.queue);
+    }
+
     fn render_shader<C: ControllerTrait>(
         &mut self,
         ctx: &GraphicsContext,
         output_view: &wgpu::TextureView,
         controller: &mut C,
         available_rect: egui::Rect,
+        time: FrameTime,
     ) {
+        let size = glam::vec2(available_rect.width(), available_rect.height()).floor();
+        if self.shader_viewport != available_rect {
+            self.shader_viewport = available_rect;
+            controller.resize(size.as_uvec2());
+            if let Some(pass_targets) = &mut self.pass_targets {
+                pass_targets.resize(&ctx.device, size.as_uvec2());
+            }
+        }
+        let offset = self.shader_offset();
+        let push_constants = controller.prepare_render(offset, time);
+        let bytes = bytemuck::bytes_of(&push_constants);
+        #[cfg(feature = "emulate_constants")]
+        {
+            ctx.queue
+                .write_buffer(&self.bind_group_data.last().unwrap().buffers[0], 0, bytes);
+        }
+
         let mut encoder = ctx
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Shader Encoder"),
             });
+        let timer = self.profiler.start(Pass::Shader);
+        if let Some(pass_targets) = &self.pass_targets {
+            for (i, pipeline) in self.pipelines.passes.iter().enumerate() {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some(&format!("Shader Pass {i}")),
+                    occlusion_query_set: None,
+                    timestamp_writes: timer.render_writes(i == 0, false),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: pass_targets.target(i),
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                rpass.set_pipeline(pipeline);
+                #[cfg(not(feature = "emulate_constants"))]
+                if !bytes.is_empty() {
+                    rpass.set_push_constants(wgpu::ShaderStages::FRAGMENT, 0, bytes);
+                }
+                for (set, bind_group) in self
+                    .bind_groups(Some(pass_targets.bind_group(i)))
+                    .enumerate()
+                {
+                    rpass.set_bind_group(set as u32, bind_group, &[]);
+                }
+                rpass.draw(0..3, 0..1);
+            }
+        }
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Shader Render Pass"),
                 occlusion_query_set: None,
-                timestamp_writes: None,
+                timestamp_writes: timer.render_writes(self.pass_targets.is_none(), true),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: output_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
+                        load: wgpu::LoadOp::Clear(self.clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
 
-            let size = glam::vec2(available_rect.width(), available_rect.height()).floor();
-            if self.shader_viewport != available_rect {
-                self.shader_viewport = available_rect;
-                controller.resize(size.as_uvec2());
-            }
-            let offset = self.shader_offset();
             rpass.set_viewport(offset.x, offset.y, size.x, size.y, 0.0, 1.0);
 
             rpass.set_pipeline(&self.pipelines.render);
-            {
-                let push_constants = controller.prepare_render(offset);
-                let bytes = bytemuck::bytes_of(&push_constants);
-                #[cfg(not(feature = "emulate_constants"))]
+            #[cfg(not(feature = "emulate_constants"))]
+            if !bytes.is_empty() {
                 rpass.set_push_constants(wgpu::ShaderStages::FRAGMENT, 0, bytes);
-                #[cfg(feature = "emulate_constants")]
-                {
-                    ctx.queue.write_buffer(
-                        &self.bind_group_data.last().unwrap().buffers[0],
-                        0,
-                        bytes,
-                    );
-                }
             }
-            for (i, bind_group_data) in self.bind_group_data.iter().enumerate() {
-                rpass.set_bind_group(i as u32, &bind_group_data.bind_group, &[]);
+            let pass_bind_group = self
+                .pass_targets
+                .as_ref()
+                .map(|pass_targets| pass_targets.bind_group(pass_targets.len()));
+            for (i, bind_group) in self.bind_groups(pass_bind_group).enumerate() {
+                rpass.set_bind_group(i as u32, bind_group, &[]);
             }
             rpass.draw(0..3, 0..1);
         }
 
         ctx.queue.submit(Some(encoder.finish()));
+        timer.finish();
+        if let Some(pass_targets) = &mut self.pass_targets {
+            pass_targets.finish_frame();
+        }
+    }
+
+    /// Bind groups in descriptor set order
+    /// The pass textures come right after the described buffers
+    fn bind_groups<'a>(
+        &'a self,
+        pass_bind_group: Option<&'a wgpu::BindGroup>,
+    ) -> impl Iterator<Item = &'a wgpu::BindGroup> {
+        #[cfg(not(feature = "emulate_constants"))]
+        let (emulated, described) = (None::<&BindGroupData>, &self.bind_group_data[..]);
+        #[cfg(feature = "emulate_constants")]
+        let (emulated, described) = self
+            .bind_group_data
+            .split_last()
+            .map(|(emulated, described)| (Some(emulated), described))
+            .unwrap();
+        described
+            .iter()
+            .map(|data| &data.bind_group)
+            .chain(pass_bind_group)
+            .chain(emulated.map(|data| &data.bind_group))
     }
 
     fn render_ui<C: ControllerTrait>(
         &mut self,
         ctx: &GraphicsContext,
-        output_view: &wgpu::TextureView,
+        output: &wgpu::Texture,
         window: &Window,
         ui: &mut Ui,
         ui_state: &mut UiState,
         controller: &mut C,
     ) {
+        let output_view = &output.create_view(&wgpu::TextureViewDescriptor::default());
         let (clipped_primitives, textures_delta, available_rect, pixels_per_point) =
             ui.prepare(window, ui_state, controller, ctx);
+        if !ui_state.reallocate.is_empty() {
+            let requests = std::mem::take(&mut ui_state.reallocate);
+            self.reallocate(ctx, controller, &requests);
+        }
 
         if available_rect.width() > 0.0 && available_rect.height() > 0.0 {
             self.render_shader(
@@ -212,9 +410,13 @@ impl RenderPass {
                 output_view,
                 controller,
                 available_rect * pixels_per_point,
+                ui_state.clock.frame_time(),
             );
         }
 
+        #[cfg(not(target_arch = "wasm32"))]
+        self.capture(ctx, output, ui_state, false);
+
         let screen_descriptor = egui_wgpu::ScreenDescriptor {
             size_in_pixels: [ctx.config.width, ctx.config.height],
             pixels_per_point,
@@ -225,6 +427,7 @@ impl RenderPass {
                 .update_texture(&ctx.device, &ctx.queue, *id, delta);
         }
 
+        let timer = self.profiler.start(Pass::Ui);
         let mut encoder = ctx
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -243,7 +446,7 @@ impl RenderPass {
             let rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("UI Render Pass"),
                 occlusion_query_set: None,
-                timestamp_writes: None,
+                timestamp_writes: timer.render_writes(true, true),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: output_view,
                     resolve_target: None,
@@ -267,52 +470,217 @@ impl RenderPass {
         }
 
         ctx.queue.submit(Some(encoder.finish()));
+        timer.finish();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.capture(ctx, output, ui_state, true);
     }
 
+    /// Handle screenshots and recording that want the frame in its current state
+    #[cfg(not(target_arch = "wasm32"))]
+    fn capture(
+        &self,
+        ctx: &GraphicsContext,
+        output: &wgpu::Texture,
+        ui_state: &mut UiState,
+        ui_drawn: bool,
+    ) {
+        if let Some(screenshot) = ui_state.screenshot.take_if(|s| s.include_ui == ui_drawn) {
+            screenshot.save(ctx, output, self.shader_viewport);
+        }
+        let Some(recorder) = ui_state.recorder.as_mut() else {
+            return;
+        };
+        if recorder.include_ui() != ui_drawn {
+            return;
+        }
+        if let Some(image) = read_viewport(ctx, output, self.shader_viewport) {
+            if let Err(err) = recorder.write_frame(&image) {
+                log::error!("Stopped recording: {err}");
+                ui_state.stop_recording();
+            }
+        }
+    }
+
+    /// Swap in pipelines for a rebuilt shader, keeping the current ones if anything is wrong with it
+    /// Buffers are described again since the shader may have changed its bindings
     #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
-    pub fn new_module(&mut self, ctx: &GraphicsContext, shader_path: &std::path::Path) {
-        self.pipelines = create_pipelines(
+    pub fn new_module<C: ControllerTrait>(
+        &mut self,
+        ctx: &GraphicsContext,
+        shader: &ShaderModules,
+        controller: &mut C,
+    ) -> Result<(), PipelineError> {
+        let buffer_data = &controller.describe_buffers();
+        let reflected_bindings = shader
+            .entry_points()
+            .map(|reflected| {
+                active_bindings(
+                    &reflected,
+                    &self.entry_points,
+                    &self.passes,
+                    #[cfg(feature = "compute")]
+                    &self.compute_entry_points,
+                )
+            })
+            .transpose()?;
+        let layout_entries = layout_entries(
+            buffer_data,
+            reflected_bindings.as_deref(),
+            self.layout_source,
+            reserved_sets(self.pass_targets.as_ref()),
+        )
+        .map_err(PipelineError::BindingMismatches)?;
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let bind_group_layouts = create_bind_group_layouts(ctx, &layout_entries);
+        let pipeline_layouts = create_pipeline_layouts(
+            ctx,
+            &bind_group_layouts,
+            self.pass_targets.as_ref(),
+            self.push_constant_sizes,
+        );
+        let result = create_pipelines(
             &ctx.device,
-            &self.pipeline_layouts,
+            &pipeline_layouts,
             ctx.config.format,
-            &std::fs::read(shader_path).unwrap(),
+            &self.entry_points,
+            &self.passes,
+            #[cfg(feature = "compute")]
+            &self.compute_entry_points,
+            shader,
         );
+        // Resources that still fit are shared with the current bind groups, which stay in use until everything succeeded
+        let (bind_group_data, writable_buffers, writable_textures) = create_bind_groups(
+            ctx,
+            buffer_data,
+            &bind_group_layouts,
+            #[cfg(feature = "emulate_constants")]
+            self.push_constant_sizes,
+            &self.bind_group_data,
+            &[],
+        );
+        let validation_error = futures::executor::block_on(ctx.device.pop_error_scope());
+        self.pipelines = match (result, validation_error) {
+            (Err(err), _) => return Err(err),
+            (Ok(_), Some(err)) => return Err(PipelineError::Validation(err)),
+            (Ok(pipelines), None) => pipelines,
+        };
+
+        self.reflected_bindings = reflected_bindings;
+        self.layout_entries = layout_entries;
+        self.bind_group_layouts = bind_group_layouts;
+        self.bind_group_data = bind_group_data;
+        controller.receive_buffers(writable_buffers);
+        controller.receive_textures(writable_textures);
+        Ok(())
+    }
+
+    /// Recreate the requested resources from a fresh [`ControllerTrait::describe_buffers`]
+    /// Resources whose size changed are recreated too, the binding types must stay the same
+    pub fn reallocate<C: ControllerTrait>(
+        &mut self,
+        ctx: &GraphicsContext,
+        controller: &mut C,
+        requests: &[(usize, usize)],
+    ) {
+        let buffer_data = &controller.describe_buffers();
+        match layout_entries(
+            buffer_data,
+            self.reflected_bindings.as_deref(),
+            self.layout_source,
+            reserved_sets(self.pass_targets.as_ref()),
+        ) {
+            Ok(layout_entries) if layout_entries == self.layout_entries => {}
+            Ok(_) => {
+                log::error!(
+                    "Reallocated buffers must keep their binding types, reload the shader to change them"
+                );
+                return;
+            }
+            Err(mismatches) => {
+                log::error!("{}", PipelineError::BindingMismatches(mismatches));
+                return;
+            }
+        }
+        let (writable_buffers, writable_textures) =
+            self.rebuild_bind_groups(ctx, buffer_data, requests);
+        controller.receive_buffers(writable_buffers);
+        controller.receive_textures(writable_textures);
+    }
+
+    /// Returns the writable buffers and textures for the controller
+    fn rebuild_bind_groups(
+        &mut self,
+        ctx: &GraphicsContext,
+        buffer_data: &[Vec<BufferDescriptor>],
+        recreate: &[(usize, usize)],
+    ) -> (Vec<Arc<wgpu::Buffer>>, Vec<Arc<wgpu::Texture>>) {
+        let (bind_group_data, writable_buffers, writable_textures) = create_bind_groups(
+            ctx,
+            buffer_data,
+            &self.bind_group_layouts,
+            #[cfg(feature = "emulate_constants")]
+            self.push_constant_sizes,
+            &self.bind_group_data,
+            recreate,
+        );
+        self.bind_group_data = bind_group_data;
+        (writable_buffers, writable_textures)
     }
 
     pub fn shader_offset(&self) -> glam::Vec2 {
         glam::vec2(self.shader_viewport.left(), self.shader_viewport.top())
     }
+
+    /// The buffer described at `set` and `binding`, `None` for other kinds of bindings
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn buffer(&self, set: usize, binding: usize) -> Option<&wgpu::Buffer> {
+        match &self.bind_group_data.get(set)?.resources.get(binding)?.1 {
+            BoundResource::Buffer(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
 }
 
+/// Sets the runner binds after the described ones, see [`create_pipeline_layouts`]
+fn reserved_sets(pass_targets: Option<&PassTargets>) -> usize {
+    pass_targets.is_some() as usize + cfg!(feature = "emulate_constants") as usize
+}
+
+/// The pass textures come right after the described buffers
 fn create_pipeline_layouts(
     ctx: &GraphicsContext,
     bind_group_layouts: &[wgpu::BindGroupLayout],
+    pass_targets: Option<&PassTargets>,
+    push_constant_sizes: PushConstantSizes,
 ) -> PipelineLayouts {
-    let bind_group_layouts = &bind_group_layouts.iter().collect::<Vec<_>>();
-    let create = |push_constant_ranges| {
+    let mut bind_group_layouts = bind_group_layouts.iter().collect::<Vec<_>>();
+    if let Some(pass_targets) = pass_targets {
+        #[cfg(feature = "emulate_constants")]
+        let index = bind_group_layouts.len() - 1;
+        #[cfg(not(feature = "emulate_constants"))]
+        let index = bind_group_layouts.len();
+        bind_group_layouts.insert(index, &pass_targets.layout);
+    }
+    let bind_group_layouts = &bind_group_layouts[..];
+    let create = |push_constant_range: Option<wgpu::PushConstantRange>| {
         ctx.device
             .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: None,
                 bind_group_layouts,
-                push_constant_ranges,
+                push_constant_ranges: push_constant_range.as_slice(),
             })
     };
     PipelineLayouts {
-        render: create(&[
-            #[cfg(not(feature = "emulate_constants"))]
-            wgpu::PushConstantRange {
-                stages: wgpu::ShaderStages::FRAGMENT,
-                range: 0..128,
-            },
-        ]),
+        render: create(push_constant_range(
+            wgpu::ShaderStages::FRAGMENT,
+            push_constant_sizes.fragment,
+        )),
         #[cfg(feature = "compute")]
-        compute: create(&[
-            #[cfg(not(feature = "emulate_constants"))]
-            wgpu::PushConstantRange {
-                stages: wgpu::ShaderStages::COMPUTE,
-                range: 0..128,
-            },
-        ]),
+        compute: create(push_constant_range(
+            wgpu::ShaderStages::COMPUTE,
+            push_constant_sizes.compute,
+        )),
     }
 }
 
@@ -320,19 +688,96 @@ fn create_pipelines(
     device: &wgpu::Device,
     pipeline_layouts: &PipelineLayouts,
     surface_format: wgpu::TextureFormat,
-    shader_bytes: &[u8],
-) -> Pipelines {
-    let spirv = wgpu::util::make_spirv(shader_bytes);
-    let module = &device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: None,
-        source: spirv,
+    entry_points: &EntryPoints,
+    passes: &[PassDescriptor],
+    #[cfg(feature = "compute")] compute_entry_points: &[&str],
+    shader: &ShaderModules,
+) -> Result<Pipelines, PipelineError> {
+    let modules = shader.map(|bytes| {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::util::make_spirv(bytes),
+        })
     });
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: None,
-        layout: Some(&pipeline_layouts.render),
+    // Only multimodule shaders can lack the module of an entry point
+    let module = |name: &str, stage| {
+        modules
+            .get(name)
+            .ok_or_else(|| PipelineError::MissingEntryPoint {
+                name: name.to_owned(),
+                stage,
+                available: shader
+                    .entry_points()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|entry_point| entry_point.stage == stage)
+                    .map(|entry_point| entry_point.name)
+                    .collect(),
+            })
+    };
+    let vertex_module = module(entry_points.vertex, wgpu::ShaderStages::VERTEX)?;
+    let render_pipeline = create_render_pipeline(
+        device,
+        &pipeline_layouts.render,
+        vertex_module,
+        module(entry_points.fragment, wgpu::ShaderStages::FRAGMENT)?,
+        entry_points,
+        entry_points.fragment,
+        surface_format,
+    );
+    let pass_pipelines = passes
+        .iter()
+        .map(|pass| {
+            Ok(create_render_pipeline(
+                device,
+                &pipeline_layouts.render,
+                vertex_module,
+                module(pass.entry_point, wgpu::ShaderStages::FRAGMENT)?,
+                entry_points,
+                pass.entry_point,
+                pass.format,
+            ))
+        })
+        .collect::<Result<_, _>>()?;
+    #[cfg(feature = "compute")]
+    let compute_pipelines = compute_entry_points
+        .iter()
+        .map(|&entry_point| {
+            Ok(
+                device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(entry_point),
+                    layout: Some(&pipeline_layouts.compute),
+                    module: module(entry_point, wgpu::ShaderStages::COMPUTE)?,
+                    entry_point: Some(entry_point),
+                    compilation_options: Default::default(),
+                    cache: None,
+                }),
+            )
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Pipelines {
+        render: render_pipeline,
+        passes: pass_pipelines,
+        #[cfg(feature = "compute")]
+        compute: compute_pipelines,
+    })
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    vertex_module: &wgpu::ShaderModule,
+    fragment_module: &wgpu::ShaderModule,
+    entry_points: &EntryPoints,
+    fragment_entry_point: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(fragment_entry_point),
+        layout: Some(layout),
         vertex: wgpu::VertexState {
-            module,
-            entry_point: Some("main_vs"),
+            module: vertex_module,
+            entry_point: Some(entry_points.vertex),
             buffers: &[],
             compilation_options: Default::default(),
         },
@@ -352,10 +797,10 @@ fn create_pipelines(
             alpha_to_coverage_enabled: false,
         },
         fragment: Some(wgpu::FragmentState {
-            module,
-            entry_point: Some("main_fs"),
+            module: fragment_module,
+            entry_point: Some(fragment_entry_point),
             targets: &[Some(wgpu::ColorTargetState {
-                format: surface_format,
+                format,
                 blend: None,
                 write_mask: wgpu::ColorWrites::ALL,
             })],
@@ -363,49 +808,50 @@ fn create_pipelines(
         }),
         multiview: None,
         cache: None,
-    });
+    })
+}
+
+/// Check that every entry point that ends up in a pipeline exists, and return the bindings they use
+/// Modules that couldn't be parsed aren't checked, wgpu will report them instead
+fn active_bindings(
+    reflected: &[EntryPoint],
+    entry_points: &EntryPoints,
+    passes: &[PassDescriptor],
+    #[cfg(feature = "compute")] compute_entry_points: &[&str],
+) -> Result<Vec<ReflectedBinding>, PipelineError> {
+    let mut names = vec![
+        (entry_points.vertex, wgpu::ShaderStages::VERTEX),
+        (entry_points.fragment, wgpu::ShaderStages::FRAGMENT),
+    ];
+    names.extend(
+        passes
+            .iter()
+            .map(|pass| (pass.entry_point, wgpu::ShaderStages::FRAGMENT)),
+    );
     #[cfg(feature = "compute")]
-    let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-        label: None,
-        layout: Some(&pipeline_layouts.compute),
-        module,
-        entry_point: Some("main_cs"),
-        compilation_options: Default::default(),
-        cache: None,
-    });
-    Pipelines {
-        render: render_pipeline,
-        #[cfg(feature = "compute")]
-        compute: compute_pipeline,
+    names.extend(
+        compute_entry_points
+            .iter()
+            .map(|&name| (name, wgpu::ShaderStages::COMPUTE)),
+    );
+    for &(name, stage) in &names {
+        reflection::check_entry_point(reflected, name, stage)?;
     }
+    let names = names.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+    Ok(reflection::used_bindings(reflected, &names))
 }
 
 fn create_bind_group_layouts(
     ctx: &GraphicsContext,
-    buffer_descriptors2: &[Vec<BufferDescriptor>],
+    layout_entries: &[Vec<wgpu::BindGroupLayoutEntry>],
 ) -> Vec<wgpu::BindGroupLayout> {
-    let layouts = buffer_descriptors2
+    let layouts = layout_entries
         .iter()
         .enumerate()
-        .map(|(layout_index, descriptors)| {
+        .map(|(layout_index, entries)| {
             ctx.device
                 .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    entries: &descriptors
-                        .iter()
-                        .enumerate()
-                        .map(|(i, descriptor)| wgpu::BindGroupLayoutEntry {
-                            binding: i as u32,
-                            visibility: descriptor.shader_stages,
-                            ty: wgpu::BindingType::Buffer {
-                                ty: wgpu::BufferBindingType::Storage {
-                                    read_only: descriptor.read_only,
-                                },
-                                has_dynamic_offset: false,
-                                min_binding_size: None,
-                            },
-                            count: None,
-                        })
-                        .collect::<Vec<_>>(),
+                    entries,
                     label: Some(&format!("bind_group_layout {}", layout_index)),
                 })
         });
@@ -443,56 +889,72 @@ fn create_bind_group_layouts(
     layouts.collect()
 }
 
+/// Resources in `previous` are reused where the descriptor at the same set and binding
+/// has the same [`ResourceKey`] and isn't in `recreate`, the rest are created from their descriptors
 fn create_bind_groups(
     ctx: &GraphicsContext,
     buffer_descriptors2: &[Vec<BufferDescriptor>],
     bind_group_layouts: &[wgpu::BindGroupLayout],
-) -> (Vec<BindGroupData>, Vec<wgpu::Buffer>) {
+    #[cfg(feature = "emulate_constants")] push_constant_sizes: PushConstantSizes,
+    previous: &[BindGroupData],
+    recreate: &[(usize, usize)],
+) -> (
+    Vec<BindGroupData>,
+    Vec<Arc<wgpu::Buffer>>,
+    Vec<Arc<wgpu::Texture>>,
+) {
     let mut writable_buffers = vec![];
+    let mut writable_textures = vec![];
     let bind_group_data = buffer_descriptors2
         .iter()
         .zip(bind_group_layouts)
         .enumerate()
         .map(|(layout_index, (descriptors, layout))| {
-            let buffers = descriptors
+            let resources = descriptors
                 .iter()
-                .map(|descriptor| {
-                    let mut usage = wgpu::BufferUsages::STORAGE;
-                    if descriptor.cpu_writable {
-                        usage |= wgpu::BufferUsages::COPY_DST;
-                    }
-                    let buffer = ctx
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Bind Group Buffer"),
-                            contents: descriptor.data,
-                            usage,
-                        });
-                    buffer
+                .enumerate()
+                .map(|(i, descriptor)| {
+                    let key = descriptor.key();
+                    let reused = previous
+                        .get(layout_index)
+                        .and_then(|data| data.resources.get(i))
+                        .filter(|(previous_key, _)| {
+                            *previous_key == key && !recreate.contains(&(layout_index, i))
+                        })
+                        .cloned();
+                    reused.unwrap_or_else(|| {
+                        (key, descriptor.create_resource(&ctx.device, &ctx.queue))
+                    })
                 })
                 .collect::<Vec<_>>();
-            let bind_group_data = BindGroupData {
+            for ((_, resource), descriptor) in resources.iter().zip(descriptors) {
+                if descriptor.cpu_writable {
+                    match resource {
+                        BoundResource::Buffer(buffer) => writable_buffers.push(buffer.clone()),
+                        BoundResource::Texture(texture, _) => {
+                            writable_textures.push(texture.clone())
+                        }
+                        BoundResource::Sampler(_) => {}
+                    }
+                }
+            }
+            BindGroupData {
                 bind_group: ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
                     layout,
-                    entries: &buffers
+                    entries: &resources
                         .iter()
                         .enumerate()
-                        .map(|(i, buffer)| wgpu::BindGroupEntry {
+                        .map(|(i, (_, resource))| wgpu::BindGroupEntry {
                             binding: i as u32,
-                            resource: buffer.as_entire_binding(),
+                            resource: resource.as_binding_resource(),
                         })
                         .collect::<Vec<_>>(),
                     label: Some(&format!("bind_group {}", layout_index)),
                 }),
                 #[cfg(feature = "emulate_constants")]
                 buffers: vec![],
-            };
-            for (buffer, descriptor) in buffers.into_iter().zip(descriptors) {
-                if descriptor.cpu_writable {
-                    writable_buffers.push(buffer);
-                }
+                resources,
             }
-            bind_group_data
         });
     #[cfg(feature = "emulate_constants")]
     let bind_group_data = {
@@ -502,7 +964,7 @@ fn create_bind_groups(
                 ctx.device
                     .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                         label: None,
-                        contents: &[0; 128],
+                        contents: &emulated_contents(push_constant_sizes.fragment),
                         usage,
                     });
             #[cfg(feature = "compute")]
@@ -510,7 +972,7 @@ fn create_bind_groups(
                 ctx.device
                     .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                         label: None,
-                        contents: &[0; 128],
+                        contents: &emulated_contents(push_constant_sizes.compute),
                         usage,
                     });
             BindGroupData {
@@ -534,8 +996,19 @@ fn create_bind_groups(
                     #[cfg(feature = "compute")]
                     compute_constants_buffer,
                 ],
+                resources: vec![],
             }
         }])
     };
-    (bind_group_data.collect(), writable_buffers)
+    (
+        bind_group_data.collect(),
+        writable_buffers,
+        writable_textures,
+    )
+}
+
+/// Zeroed contents for an emulated push constant buffer, bindings can't be empty
+#[cfg(feature = "emulate_constants")]
+fn emulated_contents(size: u32) -> Vec<u8> {
+    vec![0; size.max(wgpu::PUSH_CONSTANT_ALIGNMENT) as usize]
 }
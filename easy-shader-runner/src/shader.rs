@@ -1,15 +1,70 @@
-use spirv_builder::{CompileResult, MetadataPrintout, ModuleResult, SpirvBuilder};
+use crate::shader_modules::ShaderModules;
+use spirv_builder::{MetadataPrintout, SpirvBuilder};
 use std::path::{Path, PathBuf};
 #[cfg(feature = "hot-reload-shader")]
 use {
     crate::{controller::ControllerTrait, user_event::CustomEvent},
     egui_winit::winit::event_loop::EventLoopProxy,
+    notify::Watcher,
+    spirv_builder::ModuleResult,
+    std::io::{BufRead, Read},
 };
 
+/// Set for the copy of the process that builds the shader, see [`build`]
+#[cfg(feature = "hot-reload-shader")]
+const BUILD_PROCESS_VAR: &str = "EASY_SHADER_RUNNER_BUILD_SHADER";
+/// Prefixes the lines the build process prints for each compiled module
+#[cfg(feature = "hot-reload-shader")]
+const MODULE_PREFIX: &str = "easy-shader-runner module\t";
+
 pub fn compile_shader<#[cfg(feature = "hot-reload-shader")] C: ControllerTrait + Send>(
     #[cfg(feature = "hot-reload-shader")] event_proxy: EventLoopProxy<CustomEvent<C>>,
     relative_crate_path: impl AsRef<Path>,
-) -> PathBuf {
+) -> ShaderModules<'static> {
+    #[cfg(feature = "hot-reload-shader")]
+    let initial_module = {
+        let crate_path = crate_path(relative_crate_path.as_ref());
+        watch(
+            crate_path.parent().unwrap_or(&crate_path).to_owned(),
+            event_proxy,
+        )
+    };
+    #[cfg(not(feature = "hot-reload-shader"))]
+    let initial_module = shader_builder(relative_crate_path.as_ref())
+        .build()
+        .unwrap()
+        .module;
+    ShaderModules::read(&initial_module).expect("Failed to read the compiled shader")
+}
+
+/// In the process started by [`build`], build the shader, print where the modules are and exit
+#[cfg(feature = "hot-reload-shader")]
+pub fn exit_if_build_process(relative_crate_path: impl AsRef<Path>) {
+    if std::env::var_os(BUILD_PROCESS_VAR).is_none() {
+        return;
+    }
+    let Ok(result) = shader_builder(relative_crate_path.as_ref()).build() else {
+        std::process::exit(1);
+    };
+    match result.module {
+        ModuleResult::SingleModule(path) => println!("{MODULE_PREFIX}{}", path.display()),
+        ModuleResult::MultiModule(paths) => {
+            for (entry_point, path) in paths {
+                println!("{MODULE_PREFIX}{entry_point}\t{}", path.display());
+            }
+        }
+    }
+    std::process::exit(0);
+}
+
+fn crate_path(relative_crate_path: &Path) -> PathBuf {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    [Path::new(&manifest_dir), relative_crate_path]
+        .iter()
+        .collect()
+}
+
+fn shader_builder(relative_crate_path: &Path) -> SpirvBuilder {
     // Hack: spirv_builder builds into a custom directory if running under cargo, to not
     // deadlock, and the default target directory if not. However, packages like `proc-macro2`
     // have different configurations when being built here vs. when building
@@ -21,42 +76,158 @@ pub fn compile_shader<#[cfg(feature = "hot-reload-shader")] C: ControllerTrait +
         option_env!("SHADERS_TARGET_DIR").unwrap_or(env!("OUT_DIR")),
     );
     std::env::set_var("PROFILE", env!("PROFILE"));
-    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
-    let crate_path = [Path::new(&manifest_dir), relative_crate_path.as_ref()]
-        .iter()
-        .collect::<PathBuf>();
-
-    let builder = SpirvBuilder::new(crate_path, "spirv-unknown-vulkan1.1")
+    SpirvBuilder::new(crate_path(relative_crate_path), "spirv-unknown-vulkan1.1")
         .print_metadata(MetadataPrintout::None)
         .shader_crate_features([
             #[cfg(feature = "emulate_constants")]
             "emulate_constants".into(),
         ])
-        .shader_panic_strategy(spirv_builder::ShaderPanicStrategy::SilentExit);
-    fn handle_compile_result(compile_result: CompileResult) -> PathBuf {
-        match compile_result.module {
-            ModuleResult::SingleModule(result) => result,
-            ModuleResult::MultiModule(_) => {
-                panic!("expected `ModuleResult::SingleModule")
+        .shader_panic_strategy(spirv_builder::ShaderPanicStrategy::SilentExit)
+        .multimodule(cfg!(feature = "multimodule"))
+}
+
+/// Rebuild whenever a source file next to the shader crate changes, e.g. a shared crate
+/// `SpirvBuilder::watch` silently drops failed builds, so this reports them as [`CustomEvent::ShaderError`]
+/// with the compiler diagnostics
+/// There is no window to show an error in until the first build succeeds,
+/// so until then the diagnostics are only printed to the terminal and this waits for a change
+#[cfg(feature = "hot-reload-shader")]
+fn watch<C: ControllerTrait + Send>(
+    watch_path: PathBuf,
+    event_proxy: EventLoopProxy<CustomEvent<C>>,
+) -> ModuleResult {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|event| is_source_change(&event)) {
+            let _ = tx.send(());
+        }
+    })
+    .expect("Failed to create shader file watcher");
+    watcher
+        .watch(&watch_path, notify::RecursiveMode::Recursive)
+        .expect("Failed to watch the shader crate");
+    let wait_for_change = move || {
+        rx.recv().expect("Shader file watcher stopped");
+        // Editors tend to touch several files per save
+        while rx
+            .recv_timeout(std::time::Duration::from_millis(100))
+            .is_ok()
+        {}
+    };
+    let initial_module = loop {
+        match build() {
+            Ok(module) => break module,
+            Err(_) => {
+                log::error!("Failed to build the shader, waiting for changes");
+                wait_for_change();
             }
         }
-    }
-    #[cfg(feature = "hot-reload-shader")]
-    let initial_result = builder
-        .watch(move |compile_result, first| {
-            if let Some(first) = first {
-                first.submit(compile_result);
-            } else {
-                std::assert!(event_proxy
-                    .send_event(CustomEvent::NewModule(handle_compile_result(
-                        compile_result
-                    )))
-                    .is_ok())
+    };
+    std::thread::spawn(move || {
+        let _watcher = watcher;
+        loop {
+            wait_for_change();
+            let event = match build() {
+                Ok(module) => match ShaderModules::read(&module) {
+                    Ok(shader) => CustomEvent::NewModule(shader),
+                    Err(err) => CustomEvent::ShaderError(format!(
+                        "Failed to read the rebuilt shader: {err}"
+                    )),
+                },
+                Err(err) => CustomEvent::ShaderError(err),
+            };
+            if event_proxy.send_event(event).is_err() {
+                break;
             }
+        }
+    });
+    initial_module
+}
+
+/// Build the shader in a copy of this process, returning the compiler diagnostics on failure
+/// `spirv-builder` lets cargo print them to stderr, which is only ours to read in another process
+/// The copy is started with the same arguments and stops in [`exit_if_build_process`]
+/// Its stderr is passed on to the terminal as it arrives
+#[cfg(feature = "hot-reload-shader")]
+fn build() -> Result<ModuleResult, String> {
+    let failed = |err: std::io::Error| format!("Failed to start the shader build: {err}");
+    let mut child = std::process::Command::new(std::env::current_exe().map_err(failed)?)
+        .args(std::env::args_os().skip(1))
+        .env(BUILD_PROCESS_VAR, "1")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(failed)?;
+    let mut stdout = child.stdout.take().unwrap();
+    let stdout = std::thread::spawn(move || {
+        let mut output = String::new();
+        let _ = stdout.read_to_string(&mut output);
+        output
+    });
+    let mut diagnostics = vec![];
+    for line in std::io::BufReader::new(child.stderr.take().unwrap())
+        .lines()
+        .map_while(Result::ok)
+    {
+        eprintln!("{line}");
+        if !is_cargo_status(&line) {
+            diagnostics.push(line);
+        }
+    }
+    let status = child.wait().map_err(failed)?;
+    let stdout = stdout.join().unwrap_or_default();
+    if !status.success() {
+        let diagnostics = diagnostics.join("\n");
+        return Err(if diagnostics.trim().is_empty() {
+            format!(
+                "Failed to build the shader: {status}\nSee the terminal for compiler diagnostics"
+            )
+        } else {
+            diagnostics.trim_end().to_owned()
+        });
+    }
+    let mut modules = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix(MODULE_PREFIX))
+        .map(|module| match module.split_once('\t') {
+            Some((entry_point, path)) => (Some(entry_point.to_owned()), PathBuf::from(path)),
+            None => (None, PathBuf::from(module)),
+        })
+        .peekable();
+    match modules.peek() {
+        None => Err("The shader build didn't report any module".to_owned()),
+        Some((None, path)) => Ok(ModuleResult::SingleModule(path.clone())),
+        Some(_) => Ok(ModuleResult::MultiModule(
+            modules
+                .filter_map(|(entry_point, path)| Some((entry_point?, path)))
+                .collect(),
+        )),
+    }
+}
+
+/// Progress lines like `   Compiling foo v0.1.0`, which cargo right aligns
+#[cfg(feature = "hot-reload-shader")]
+fn is_cargo_status(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    line.len() > trimmed.len()
+        && [
+            "Compiling ",
+            "Checking ",
+            "Building ",
+            "Fresh ",
+            "Blocking ",
+        ]
+        .iter()
+        .any(|status| trimmed.starts_with(status))
+}
+
+#[cfg(feature = "hot-reload-shader")]
+fn is_source_change(event: &notify::Event) -> bool {
+    !event.kind.is_access()
+        && event.paths.iter().any(|path| {
+            let is_source = path.extension().is_some_and(|ext| ext == "rs")
+                || path.file_name().is_some_and(|name| name == "Cargo.toml");
+            is_source && !path.components().any(|c| c.as_os_str() == "target")
         })
-        .expect("Configuration is correct for watching")
-        .unwrap();
-    #[cfg(not(feature = "hot-reload-shader"))]
-    let initial_result = builder.build().unwrap();
-    handle_compile_result(initial_result)
 }
@@ -0,0 +1,69 @@
+use crate::reflection::{self, EntryPoint};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+/// Either one module holding every entry point,
+/// or one module per entry point when the shader crate is compiled with `multimodule`
+pub(crate) enum Modules<T> {
+    Single(T),
+    Multi(BTreeMap<String, T>),
+}
+
+pub(crate) type ShaderModules<'a> = Modules<Cow<'a, [u8]>>;
+
+impl<T> Modules<T> {
+    /// The module containing `entry_point`
+    pub fn get(&self, entry_point: &str) -> Option<&T> {
+        match self {
+            Self::Single(module) => Some(module),
+            Self::Multi(modules) => modules.get(entry_point),
+        }
+    }
+
+    pub fn map<U>(&self, mut f: impl FnMut(&T) -> U) -> Modules<U> {
+        match self {
+            Self::Single(module) => Modules::Single(f(module)),
+            Self::Multi(modules) => Modules::Multi(
+                modules
+                    .iter()
+                    .map(|(entry_point, module)| (entry_point.clone(), f(module)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl ShaderModules<'_> {
+    /// Read the modules produced by `spirv-builder`
+    #[cfg(all(
+        any(feature = "runtime-compilation", feature = "hot-reload-shader"),
+        not(target_arch = "wasm32")
+    ))]
+    pub fn read(module: &spirv_builder::ModuleResult) -> std::io::Result<ShaderModules<'static>> {
+        Ok(match module {
+            spirv_builder::ModuleResult::SingleModule(path) => {
+                Modules::Single(std::fs::read(path)?.into())
+            }
+            spirv_builder::ModuleResult::MultiModule(paths) => Modules::Multi(
+                paths
+                    .iter()
+                    .map(|(entry_point, path)| {
+                        Ok((entry_point.clone(), std::fs::read(path)?.into()))
+                    })
+                    .collect::<std::io::Result<_>>()?,
+            ),
+        })
+    }
+
+    /// Entry points across all modules, `None` if naga can't reflect any of them
+    pub fn entry_points(&self) -> Option<Vec<EntryPoint>> {
+        match self {
+            Self::Single(bytes) => reflection::entry_points(bytes),
+            Self::Multi(modules) => modules
+                .values()
+                .map(|bytes| reflection::entry_points(bytes))
+                .collect::<Option<Vec<_>>>()
+                .map(|entry_points| entry_points.into_iter().flatten().collect()),
+        }
+    }
+}
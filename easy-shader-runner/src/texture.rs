@@ -0,0 +1,80 @@
+use crate::bind_group_buffer::{BindingKind, BufferDescriptor};
+use image::DynamicImage;
+
+/// A decoded image ready to be bound with [`Self::descriptors`]
+pub struct TextureData {
+    pub size: glam::UVec2,
+    pub format: wgpu::TextureFormat,
+    pub data: Vec<u8>,
+}
+
+impl TextureData {
+    /// Decode a PNG, JPEG or HDR image, e.g. from `include_bytes!`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, image::ImageError> {
+        Ok(Self::from_image(image::load_from_memory(bytes)?))
+    }
+
+    /// Load a PNG, JPEG or HDR image from disk
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, image::ImageError> {
+        Ok(Self::from_image(image::open(path)?))
+    }
+
+    /// 8 bit images become sRGB textures, HDR images become 32 bit float textures
+    fn from_image(image: DynamicImage) -> Self {
+        let size = glam::uvec2(image.width(), image.height());
+        match image {
+            DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => Self {
+                size,
+                format: wgpu::TextureFormat::Rgba32Float,
+                data: bytemuck::cast_slice(&image.into_rgba32f().into_raw()).to_vec(),
+            },
+            _ => Self {
+                size,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                data: image.into_rgba8().into_raw(),
+            },
+        }
+    }
+
+    /// Don't convert 8 bit images from sRGB when sampling, e.g. for lookup tables and noise
+    pub fn linear(mut self) -> Self {
+        if self.format == wgpu::TextureFormat::Rgba8UnormSrgb {
+            self.format = wgpu::TextureFormat::Rgba8Unorm;
+        }
+        self
+    }
+
+    /// The texture followed by a sampler for it, to be placed at consecutive bindings
+    /// Float textures can't be filtered, so they get a nearest neighbour sampler
+    pub fn descriptors(&self, shader_stages: wgpu::ShaderStages) -> [BufferDescriptor<'_>; 2] {
+        let filterable = self.format.sample_type(None, None)
+            == Some(wgpu::TextureSampleType::Float { filterable: true });
+        [
+            BufferDescriptor {
+                data: &self.data,
+                read_only: true,
+                shader_stages,
+                cpu_writable: false,
+                kind: BindingKind::Texture {
+                    size: self.size,
+                    format: self.format,
+                },
+            },
+            BufferDescriptor {
+                data: &[],
+                read_only: true,
+                shader_stages,
+                cpu_writable: false,
+                kind: BindingKind::Sampler {
+                    filter: if filterable {
+                        wgpu::FilterMode::Linear
+                    } else {
+                        wgpu::FilterMode::Nearest
+                    },
+                    address_mode: wgpu::AddressMode::Repeat,
+                },
+            },
+        ]
+    }
+}
@@ -0,0 +1,159 @@
+use crate::capture::Image;
+use std::{borrow::Cow, path::PathBuf};
+
+/// A file dropped onto the window, see [`crate::ControllerTrait::file_dropped`]
+#[derive(Clone)]
+pub enum DroppedFile {
+    /// Natively only the path is known, the file is read on demand
+    Path(PathBuf),
+    /// On the web the browser hands over the contents
+    Bytes { name: String, bytes: Vec<u8> },
+}
+
+impl DroppedFile {
+    /// File name without the directory
+    pub fn name(&self) -> Cow<'_, str> {
+        match self {
+            Self::Path(path) => path
+                .file_name()
+                .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy()),
+            Self::Bytes { name, .. } => name.into(),
+        }
+    }
+
+    /// Contents of the file, read from disk for [`Self::Path`]
+    pub fn read(&self) -> std::io::Result<Cow<'_, [u8]>> {
+        match self {
+            Self::Path(path) => std::fs::read(path).map(Cow::Owned),
+            Self::Bytes { bytes, .. } => Ok(bytes.into()),
+        }
+    }
+}
+
+/// Clipboard contents pasted into the window, see [`crate::ControllerTrait::paste`]
+#[derive(Clone)]
+pub enum Paste {
+    Text(String),
+    /// RGBA8 pixels, only pasted natively
+    Image(Image),
+    /// A copied file such as an image from a browser, only pasted on the web
+    File(DroppedFile),
+}
+
+/// Prefers an image, since copied images often come with a text description
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn read_clipboard() -> Option<Paste> {
+    let mut clipboard = match arboard::Clipboard::new() {
+        Ok(clipboard) => clipboard,
+        Err(err) => {
+            log::warn!("Can't access the clipboard: {err}");
+            return None;
+        }
+    };
+    if let Ok(image) = clipboard.get_image() {
+        return Some(Paste::Image(Image {
+            size: glam::uvec2(image.width as u32, image.height as u32),
+            data: image.bytes.into_owned(),
+        }));
+    }
+    clipboard.get_text().ok().map(Paste::Text)
+}
+
+/// Whether `event` is Ctrl+V, or Cmd+V on macOS
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn is_paste(
+    event: &egui_winit::winit::event::KeyEvent,
+    modifiers: egui_winit::winit::keyboard::ModifiersState,
+) -> bool {
+    use egui_winit::winit::keyboard::{Key, NamedKey};
+    let command = if cfg!(target_os = "macos") {
+        modifiers.super_key()
+    } else {
+        modifiers.control_key()
+    };
+    match &event.logical_key {
+        Key::Named(NamedKey::Paste) => true,
+        Key::Character(c) => command && c.eq_ignore_ascii_case("v"),
+        _ => false,
+    }
+}
+
+/// Forward files dropped onto the canvas and pastes into the page, winit doesn't report either on the web
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn listen<C: crate::ControllerTrait>(
+    canvas: &web_sys::HtmlCanvasElement,
+    event_proxy: egui_winit::winit::event_loop::EventLoopProxy<crate::user_event::CustomEvent<C>>,
+) {
+    use crate::user_event::CustomEvent;
+    use web_sys::wasm_bindgen::{closure::Closure, JsCast};
+
+    // Send every file once its contents are read
+    fn read_files<C: crate::ControllerTrait>(
+        files: web_sys::FileList,
+        event_proxy: &egui_winit::winit::event_loop::EventLoopProxy<CustomEvent<C>>,
+        event: fn(DroppedFile) -> CustomEvent<C>,
+    ) {
+        for file in (0..files.length()).filter_map(|i| files.get(i)) {
+            let event_proxy = event_proxy.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await {
+                    Ok(buffer) => {
+                        let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+                        let file = DroppedFile::Bytes {
+                            name: file.name(),
+                            bytes,
+                        };
+                        event_proxy.send_event(event(file)).ok();
+                    }
+                    Err(err) => log::warn!("Failed to read {}: {err:?}", file.name()),
+                }
+            });
+        }
+    }
+
+    // The browser only allows dropping where dragover is cancelled
+    let dragover = Closure::<dyn FnMut(_)>::new(|event: web_sys::DragEvent| {
+        event.prevent_default();
+    });
+    let proxy = event_proxy.clone();
+    let drop = Closure::<dyn FnMut(_)>::new(move |event: web_sys::DragEvent| {
+        // Keep the browser from opening the file instead
+        event.prevent_default();
+        if let Some(files) = event.data_transfer().and_then(|data| data.files()) {
+            read_files(files, &proxy, CustomEvent::FileDropped);
+        }
+    });
+    let paste = Closure::<dyn FnMut(_)>::new(move |event: web_sys::ClipboardEvent| {
+        let Some(data) = event.clipboard_data() else {
+            return;
+        };
+        match data.files().filter(|files| files.length() > 0) {
+            Some(files) => read_files(files, &event_proxy, |file| {
+                CustomEvent::Paste(Paste::File(file))
+            }),
+            None => {
+                if let Some(text) = data.get_data("text/plain").ok().filter(|t| !t.is_empty()) {
+                    event_proxy
+                        .send_event(CustomEvent::Paste(Paste::Text(text)))
+                        .ok();
+                }
+            }
+        }
+    });
+
+    canvas
+        .add_event_listener_with_callback("dragover", dragover.as_ref().unchecked_ref())
+        .ok();
+    canvas
+        .add_event_listener_with_callback("drop", drop.as_ref().unchecked_ref())
+        .ok();
+    if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+        document
+            .add_event_listener_with_callback("paste", paste.as_ref().unchecked_ref())
+            .ok();
+    }
+    // Listen for as long as the page is open
+    dragover.forget();
+    drop.forget();
+    paste.forget();
+}
@@ -1,4 +1,15 @@
-use crate::{controller::ControllerTrait, fps_counter::FpsCounter, GraphicsContext};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    capture::ScreenshotRequest,
+    recording::{Recorder, RecordingOptions},
+};
+use crate::{
+    clock::{Clock, DEFAULT_TIMESTEP},
+    controller::ControllerTrait,
+    frame_stats::{self, FrameStats, FrameTimeGraph, FrameTimer, FRAME_HISTORY_LEN},
+    profiler::{PassTimings, TimingSource},
+    GraphicsContext,
+};
 use egui::{
     epaint::{textures::TexturesDelta, ClippedPrimitive},
     Context,
@@ -7,31 +18,201 @@ use egui_winit::{
     winit::{event::WindowEvent, window::Window},
     State,
 };
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
+
+/// Frames of [`PassTimings`] kept for [`UiState::timing_history`]
+const TIMING_HISTORY_LEN: usize = 240;
 
 pub struct UiState {
     fps: u32,
+    frame_times: VecDeque<f32>,
+    pass_timings: PassTimings,
+    timing_source: TimingSource,
+    timing_history: VecDeque<PassTimings>,
+    /// Show a window graphing [`Self::pass_timings`] over time
+    pub show_profiler: bool,
+    pointer_over_viewport: bool,
+    /// Takes the place of the live pointer while replaying input
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    pub(crate) replayed_pointer_over_viewport: Option<bool>,
+    pub(crate) clock: Clock,
+    /// Whether [`ControllerTrait::ui`] and the profiler are drawn, the shader fills the window otherwise
+    pub show_ui: bool,
+    /// Set and binding of resources to recreate before rendering
+    pub(crate) reallocate: Vec<(usize, usize)>,
     #[cfg(not(target_arch = "wasm32"))]
     pub vsync: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) screenshot: Option<ScreenshotRequest>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) recorder: Option<Recorder>,
+    /// Whether the surface can be copied from, which recording needs
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) can_capture: bool,
+    /// Shown over everything until the next successful hot reload
+    #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+    pub(crate) shader_error: Option<String>,
 }
 
 impl UiState {
     pub fn new() -> Self {
         Self {
             fps: 0,
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY_LEN),
+            pass_timings: Default::default(),
+            timing_source: TimingSource::Cpu,
+            timing_history: VecDeque::with_capacity(TIMING_HISTORY_LEN),
+            show_profiler: false,
+            show_ui: true,
+            pointer_over_viewport: false,
+            #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+            replayed_pointer_over_viewport: None,
+            clock: Clock::new(DEFAULT_TIMESTEP, false),
+            reallocate: vec![],
             #[cfg(not(target_arch = "wasm32"))]
             vsync: true,
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            recorder: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            can_capture: true,
+            #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+            shader_error: None,
         }
     }
 
     pub fn fps(&self) -> &u32 {
         &self.fps
     }
+
+    /// Recent frame times in milliseconds, oldest first
+    pub fn frame_times(&self) -> &VecDeque<f32> {
+        &self.frame_times
+    }
+
+    /// Statistics over the last `window` frames, `None` before the second frame
+    pub fn frame_stats(&self, window: usize) -> Option<FrameStats> {
+        let skip = self.frame_times.len().saturating_sub(window);
+        FrameStats::new(self.frame_times.iter().skip(skip).copied())
+    }
+
+    /// A widget graphing [`Self::frame_times`], e.g. `ui.add(ui_state.frame_time_graph())`
+    pub fn frame_time_graph(&self) -> FrameTimeGraph<'_> {
+        FrameTimeGraph::new(self)
+    }
+
+    fn push_frame_time(&mut self, frame_time: f32) {
+        if self.frame_times.len() == FRAME_HISTORY_LEN {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time);
+        self.fps = frame_stats::fps(&self.frame_times);
+    }
+
+    /// Whether the pointer was over the shader viewport rather than egui or outside the window last frame
+    pub fn pointer_over_viewport(&self) -> bool {
+        self.pointer_over_viewport
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Pause, step or speed up the simulation time passed to the controller
+    pub fn clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+
+    /// Real seconds to tick the clock by at the start of a frame, exactly one frame of the recording while recording
+    /// `replayed` takes precedence over both that and the wall clock
+    pub(crate) fn frame_delta(&mut self, replayed: Option<f32>) -> f32 {
+        let wall = self.clock.wall_delta();
+        #[cfg(not(target_arch = "wasm32"))]
+        let replayed = replayed.or(self.recording_timestep());
+        replayed.unwrap_or(wall)
+    }
+
+    /// Time spent on compute, the shader and the UI in a recent frame
+    pub fn pass_timings(&self) -> &PassTimings {
+        &self.pass_timings
+    }
+
+    pub fn timing_source(&self) -> TimingSource {
+        self.timing_source
+    }
+
+    /// Recent [`Self::pass_timings`], oldest first
+    pub fn timing_history(&self) -> &VecDeque<PassTimings> {
+        &self.timing_history
+    }
+
+    pub(crate) fn push_timings(&mut self, timings: PassTimings, source: TimingSource) {
+        if self.timing_history.len() == TIMING_HISTORY_LEN {
+            self.timing_history.pop_front();
+        }
+        self.timing_history.push_back(timings);
+        self.pass_timings = timings;
+        self.timing_source = source;
+    }
+
+    /// Recreate the resource at `set` and `binding` from [`crate::ControllerTrait::describe_buffers`] before this frame is rendered
+    /// Use this after changing the size or contents of its data, the new handle is passed to `receive_buffers` or `receive_textures`
+    pub fn reallocate_buffer(&mut self, set: usize, binding: usize) {
+        self.reallocate.push((set, binding));
+    }
+
+    /// Save the shader viewport as a PNG named after the current time once this frame is rendered
+    /// Set `include_ui` to also capture any egui windows drawn over the viewport
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn screenshot(&mut self, include_ui: bool) {
+        self.screenshot = Some(ScreenshotRequest::new(None, include_ui));
+    }
+
+    /// Same as [`Self::screenshot`] but written to `path`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn screenshot_to(&mut self, path: impl Into<std::path::PathBuf>, include_ui: bool) {
+        self.screenshot = Some(ScreenshotRequest::new(Some(path.into()), include_ui));
+    }
+
+    /// Write every rendered frame until [`Self::stop_recording`] is called
+    /// While recording, [`crate::ControllerTrait::update`] is never cut short
+    /// Fails if the surface can't be copied from or [`RecordingOptions::fps`] is 0
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_recording(&mut self, options: RecordingOptions) -> std::io::Result<()> {
+        if !self.can_capture {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "capturing frames is not supported by this surface",
+            ));
+        }
+        self.stop_recording();
+        self.recorder = Some(Recorder::new(options)?);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// The simulated duration of each frame while recording, [`crate::FrameTime::delta`] before [`Clock::speed`] is applied
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn recording_timestep(&self) -> Option<f32> {
+        self.recorder.as_ref().map(Recorder::timestep)
+    }
 }
 
 pub struct Ui {
     egui_winit_state: State,
-    fps_counter: FpsCounter,
+    frame_timer: FrameTimer,
 }
 
 impl Ui {
@@ -50,7 +231,7 @@ impl Ui {
 
         Self {
             egui_winit_state,
-            fps_counter: FpsCounter::new(),
+            frame_timer: FrameTimer::new(),
         }
     }
 
@@ -60,6 +241,11 @@ impl Ui {
             .consumed
     }
 
+    #[cfg(target_arch = "wasm32")]
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.egui_winit_state.egui_ctx().wants_keyboard_input()
+    }
+
     pub fn prepare<C: ControllerTrait>(
         &mut self,
         window: &Window,
@@ -67,13 +253,26 @@ impl Ui {
         controller: &mut C,
         graphics_context: &GraphicsContext,
     ) -> (Vec<ClippedPrimitive>, TexturesDelta, egui::Rect, f32) {
-        ui_state.fps = self.fps_counter.tick();
+        if let Some(frame_time) = self.frame_timer.tick() {
+            ui_state.push_frame_time(frame_time);
+        }
         let raw_input = self.egui_winit_state.take_egui_input(window);
         let mut available_rect = egui::Rect::NAN;
         let full_output = self.egui_winit_state.egui_ctx().run(raw_input, |ctx| {
             self.ui(ctx, ui_state, controller, graphics_context);
             available_rect = ctx.available_rect();
         });
+        let ctx = self.egui_winit_state.egui_ctx();
+        let over = !ctx.is_pointer_over_area()
+            && ctx
+                .pointer_latest_pos()
+                .is_some_and(|pos| available_rect.contains(pos));
+        #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+        let over = ui_state.replayed_pointer_over_viewport.unwrap_or(over);
+        if over != ui_state.pointer_over_viewport {
+            ui_state.pointer_over_viewport = over;
+            controller.pointer_over_viewport(over);
+        }
         self.egui_winit_state
             .handle_platform_output(window, full_output.platform_output);
         let clipped_primitives = self
@@ -95,6 +294,123 @@ impl Ui {
         controller: &mut C,
         graphics_context: &GraphicsContext,
     ) {
-        controller.ui(ctx, ui_state, graphics_context);
+        if ui_state.show_ui {
+            controller.ui(ctx, ui_state, graphics_context);
+        }
+        if ui_state.show_ui && ui_state.show_profiler {
+            let mut open = true;
+            egui::Window::new("Pass Timings")
+                .open(&mut open)
+                .default_width(320.0)
+                .show(ctx, |ui| profiler_ui(ui, ui_state));
+            ui_state.show_profiler = open;
+        }
+        #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+        if let Some(error) = &ui_state.shader_error {
+            shader_error_overlay(ctx, error);
+        }
+    }
+}
+
+#[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+fn shader_error_overlay(ctx: &Context, error: &str) {
+    egui::Area::new(egui::Id::new("shader_error"))
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8.0, -8.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style())
+                .fill(egui::Color32::from_black_alpha(220))
+                .show(ui, |ui| {
+                    ui.label(
+                        egui::RichText::new("Shader reload failed, showing the previous shader")
+                            .strong()
+                            .color(egui::Color32::LIGHT_RED),
+                    );
+                    // Compiler diagnostics can be long, scroll rather than cover the shader
+                    egui::ScrollArea::both()
+                        .max_height(ctx.screen_rect().height() * 0.5)
+                        .max_width(ctx.screen_rect().width() - 32.0)
+                        .show(ui, |ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    egui::RichText::new(error)
+                                        .monospace()
+                                        .color(egui::Color32::LIGHT_GRAY),
+                                )
+                                .extend(),
+                            );
+                        });
+                });
+        });
+}
+
+fn profiler_ui(ui: &mut egui::Ui, ui_state: &UiState) {
+    let series = [
+        ("Compute", egui::Color32::from_rgb(255, 160, 60)),
+        ("Shader", egui::Color32::from_rgb(100, 200, 100)),
+        ("UI", egui::Color32::from_rgb(100, 160, 255)),
+    ];
+    let values = |t: &PassTimings| [t.compute, t.shader, t.ui];
+    ui.label(match ui_state.timing_source {
+        TimingSource::Gpu => "GPU time per frame",
+        TimingSource::Cpu => "CPU time per frame, timestamp queries are unsupported",
+    });
+    for ((name, color), value) in series.iter().zip(values(&ui_state.pass_timings)) {
+        ui.colored_label(*color, format!("{name}: {value:.3} ms"));
+    }
+
+    let history = &ui_state.timing_history;
+    let lines = series
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, color))| {
+            let values = history.iter().map(|timings| values(timings)[i]).collect();
+            (color, values)
+        })
+        .collect::<Vec<_>>();
+    graph(ui, 120.0, TIMING_HISTORY_LEN, 0.1, &lines);
+}
+
+/// Paint `lines` as a graph with the newest value on the right and room for `capacity` values
+/// The vertical scale fits the largest value, but never less than `min_max`
+pub(crate) fn graph(
+    ui: &mut egui::Ui,
+    height: f32,
+    capacity: usize,
+    min_max: f32,
+    lines: &[(egui::Color32, Vec<f32>)],
+) -> egui::Response {
+    let max = lines
+        .iter()
+        .flat_map(|(_, values)| values)
+        .fold(min_max, |a, &b| a.max(b));
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), height),
+        egui::Sense::hover(),
+    );
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let step = rect.width() / capacity.saturating_sub(1).max(1) as f32;
+    for (color, values) in lines {
+        let points = values
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(age, value)| {
+                egui::pos2(
+                    rect.right() - age as f32 * step,
+                    rect.bottom() - value / max * rect.height(),
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, *color)));
     }
+    painter.text(
+        rect.left_top() + egui::vec2(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{max:.2} ms"),
+        egui::FontId::monospace(10.0),
+        ui.visuals().weak_text_color(),
+    );
+    response
 }
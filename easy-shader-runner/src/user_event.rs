@@ -3,6 +3,13 @@ use crate::controller::ControllerTrait;
 
 pub enum CustomEvent<C: ControllerTrait> {
     #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
-    NewModule(std::path::PathBuf),
+    NewModule(crate::shader_modules::ShaderModules<'static>),
+    #[cfg(all(feature = "hot-reload-shader", not(target_arch = "wasm32")))]
+    ShaderError(String),
     CreateWindow(Graphics<C>),
+    /// Winit reports neither on the web, see [`crate::transfer::listen`]
+    #[cfg(target_arch = "wasm32")]
+    FileDropped(crate::transfer::DroppedFile),
+    #[cfg(target_arch = "wasm32")]
+    Paste(crate::transfer::Paste),
 }
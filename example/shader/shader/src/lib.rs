@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(target_arch = "spirv", no_std)]
 
 use grid::GridRefMut;
 use push_constants::shader::*;
@@ -18,17 +18,16 @@ pub fn main_fs(
     #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] cell_grid: &mut [CellState],
     output: &mut Vec4,
 ) {
-    let mut cell_grid = GridRefMut::new(DIM, cell_grid);
+    let grid_size = constants.grid_size.as_uvec2();
+    let mut cell_grid = GridRefMut::new(grid_size, cell_grid);
     let coord = frag_coord.xy() - constants.translate;
     let i = ((coord / constants.size.as_vec2() / constants.camera_zoom
         + constants.camera_translate)
-        * DIM.as_vec2())
+        * grid_size.as_vec2())
     .as_uvec2();
 
-    if constants.mouse_button_pressed & 1 == 1 {
-        if constants.cursor.distance_squared(coord) < 0.5 {
-            cell_grid.set(i, CellState::On);
-        }
+    if constants.mouse_button_pressed & 1 == 1 && constants.cursor.distance_squared(coord) < 0.5 {
+        cell_grid.set(i, CellState::On);
     }
 
     let col = match cell_grid.get(i) {
@@ -61,8 +60,12 @@ pub fn main_cs(
     constants: &ComputeConstants,
     #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] cell_grid: &mut [CellState],
 ) {
-    let mut cell_grid = GridRefMut::new(DIM, cell_grid);
+    let grid_size = constants.grid_size.as_uvec2();
     let index = gid.xy();
+    if index.x >= grid_size.x || index.y >= grid_size.y {
+        return;
+    }
+    let mut cell_grid = GridRefMut::new(grid_size, cell_grid);
     let val = cell_grid.get(index);
 
     if constants.transition.into() {
@@ -86,7 +89,7 @@ pub fn main_cs(
                 continue;
             }
             let index = (index.as_ivec2() + ij)
-                .rem_euclid(DIM.as_ivec2())
+                .rem_euclid(grid_size.as_ivec2())
                 .as_uvec2();
 
             let val = cell_grid.get(index);
@@ -96,7 +99,7 @@ pub fn main_cs(
         }
     }
 
-    if matches!(val, CellState::On) && (count < 2 || count > 3) {
+    if matches!(val, CellState::On) && !matches!(count, 2 | 3) {
         cell_grid.set(index, CellState::Dying);
     } else if matches!(val, CellState::Off) && count == 3 {
         cell_grid.set(index, CellState::Spawning);
@@ -5,9 +5,11 @@ pub mod push_constants;
 
 use glam::*;
 
+/// Initial size of the cell grid
 pub const DIM: UVec2 = UVec2::splat(192);
 
 #[derive(Clone, Copy, Default, bytemuck::NoUninit)]
+#[cfg_attr(not(target_arch = "spirv"), derive(bytemuck::CheckedBitPattern))]
 #[repr(u32)]
 pub enum CellState {
     #[default]
@@ -13,6 +13,7 @@ pub struct FragmentConstants {
     pub camera_translate: Vec2,
     pub camera_zoom: f32,
     pub debug: Bool,
+    pub grid_size: Size,
 }
 
 #[derive(Copy, Clone, Debug, NoUninit)]
@@ -22,4 +23,5 @@ pub struct ComputeConstants {
     pub time: f32,
     pub zoom: f32,
     pub transition: Bool,
+    pub grid_size: Size,
 }
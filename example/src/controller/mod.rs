@@ -1,17 +1,19 @@
 use crate::Options;
-use easy_shader_runner::{egui, wgpu, winit, BufferDescriptor, ControllerTrait, UiState};
+#[cfg(not(target_arch = "wasm32"))]
+use easy_shader_runner::RecordingOptions;
+use easy_shader_runner::{
+    egui, wgpu, winit, BindingKind, BufferDescriptor, ComputeEntry, ControllerTrait, DroppedFile,
+    FrameTime, KeyAction, KeyBindings, Paste, UiState,
+};
 use glam::*;
 use shared::push_constants::shader::*;
 use shared::*;
-use simulation_runner::SimulationRunner;
-use web_time::Instant;
+use std::sync::Arc;
 use winit::{
-    event::{ElementState, KeyEvent, MouseButton},
+    event::{ElementState, MouseButton, TouchPhase},
     keyboard::{Key, NamedKey},
 };
 
-mod simulation_runner;
-
 struct Camera {
     zoom: f32,
     translate: Vec2,
@@ -28,57 +30,118 @@ impl Default for Camera {
 
 pub struct Controller {
     size: UVec2,
-    start: Instant,
+    time: FrameTime,
     cursor: Vec2,
     prev_cursor: Vec2,
     mouse_button_pressed: u32,
+    pointer_over_viewport: bool,
+    touches: Vec<(u64, Vec2)>,
     camera: Camera,
     debug: bool,
     cell_grid: grid::Grid<CellState>,
     transition: bool,
-    simulation_runner: SimulationRunner,
-    buffer: Option<wgpu::Buffer>,
+    /// Write `cell_grid` to the GPU on the next frame, e.g. after loading a pattern
+    upload_grid: bool,
+    /// Grid size picked on the slider, applied once it is let go
+    pending_grid_size: Option<u32>,
+    buffer: Option<Arc<wgpu::Buffer>>,
 }
 
 impl Controller {
     pub fn new(options: &Options) -> Self {
-        let now = Instant::now();
-
-        let mut cell_grid = grid::Grid::new(DIM);
-        {
-            let seed = [
-                // Initial configuration
-                [0, 1, 0],
-                [1, 1, 0],
-                [0, 1, 1],
-            ];
-            let p = DIM / 2;
-            for (i, row) in seed.into_iter().enumerate() {
-                for (j, val) in row.into_iter().enumerate() {
-                    if val != 0 {
-                        cell_grid.set(p + uvec2(i as u32, j as u32), CellState::On);
-                    }
-                }
-            }
-        }
-
         Self {
             size: UVec2::ZERO,
-            start: now,
+            time: Default::default(),
             cursor: Vec2::ZERO,
             prev_cursor: Vec2::ZERO,
             mouse_button_pressed: 0,
+            pointer_over_viewport: false,
+            touches: vec![],
             camera: Default::default(),
             debug: options.debug,
-            cell_grid,
+            cell_grid: seeded_grid(DIM),
             transition: false,
-            simulation_runner: SimulationRunner::new(now, options.debug),
+            upload_grid: false,
+            pending_grid_size: None,
             buffer: None,
         }
     }
+
+    fn zoom(&mut self, factor: f32, center: Vec2) {
+        let prev_zoom = self.camera.zoom;
+        self.camera.zoom = (prev_zoom * factor).clamp(1.0, 100.0);
+        let dif = 1.0 / prev_zoom - 1.0 / self.camera.zoom;
+        self.camera.translate += dif * center / self.size.as_vec2();
+        self.camera.translate = self
+            .camera
+            .translate
+            .clamp(Vec2::ZERO, Vec2::splat(1.0 - 1.0 / self.camera.zoom));
+    }
+
+    fn load_pattern(&mut self, pattern: &str) {
+        match pattern_grid(self.cell_grid.size, pattern) {
+            Some(cell_grid) => {
+                self.cell_grid = cell_grid;
+                self.upload_grid = true;
+            }
+            None => log::warn!("Not a plaintext pattern that fits the grid"),
+        }
+    }
+
+    fn pinch_distance(&self) -> Option<f32> {
+        match self.touches.as_slice() {
+            [(_, a), (_, b)] => Some(a.distance(*b)),
+            _ => None,
+        }
+    }
+}
+
+fn seeded_grid(size: UVec2) -> grid::Grid<CellState> {
+    let mut cell_grid = grid::Grid::new(size);
+    let seed = [
+        // Initial configuration
+        [0, 1, 0],
+        [1, 1, 0],
+        [0, 1, 1],
+    ];
+    let p = size / 2;
+    for (i, row) in seed.into_iter().enumerate() {
+        for (j, val) in row.into_iter().enumerate() {
+            if val != 0 {
+                cell_grid.set(p + uvec2(i as u32, j as u32), CellState::On);
+            }
+        }
+    }
+    cell_grid
+}
+
+/// Parse a pattern in the plaintext format, `O` or `*` for live cells and lines starting with `!` ignored
+fn pattern_grid(size: UVec2, pattern: &str) -> Option<grid::Grid<CellState>> {
+    let rows = pattern
+        .lines()
+        .filter(|line| !line.starts_with('!'))
+        .collect::<Vec<_>>();
+    let width = rows.iter().map(|row| row.chars().count()).max()? as u32;
+    let height = rows.len() as u32;
+    if width > size.x || height > size.y || !pattern.contains(['O', '*']) {
+        return None;
+    }
+    let mut cell_grid = grid::Grid::new(size);
+    let p = (size - uvec2(width, height)) / 2;
+    for (y, row) in rows.into_iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if matches!(c, 'O' | '*') {
+                cell_grid.set(p + uvec2(x as u32, y as u32), CellState::On);
+            }
+        }
+    }
+    Some(cell_grid)
 }
 
 impl ControllerTrait for Controller {
+    type PushConstants = FragmentConstants;
+    type ComputePushConstants = ComputeConstants;
+
     fn resize(&mut self, size: UVec2) {
         self.size = size;
     }
@@ -87,15 +150,22 @@ impl ControllerTrait for Controller {
         self.cursor = position.as_vec2();
     }
 
+    fn cursor_left(&mut self) {
+        self.mouse_button_pressed = 0;
+    }
+
+    fn pointer_over_viewport(&mut self, over: bool) {
+        self.pointer_over_viewport = over;
+    }
+
+    fn focused(&mut self, focused: bool) {
+        if !focused {
+            self.mouse_button_pressed = 0;
+        }
+    }
+
     fn mouse_scroll(&mut self, delta: DVec2) {
-        let prev_zoom = self.camera.zoom;
-        self.camera.zoom = (prev_zoom * (1.0 + delta.y as f32 * 0.1)).clamp(1.0, 100.0);
-        let dif = 1.0 / prev_zoom - 1.0 / self.camera.zoom;
-        self.camera.translate += dif * self.cursor / self.size.as_vec2();
-        self.camera.translate = self
-            .camera
-            .translate
-            .clamp(Vec2::ZERO, Vec2::splat(1.0 - 1.0 / self.camera.zoom));
+        self.zoom(1.0 + delta.y as f32 * 0.1, self.cursor);
     }
 
     fn mouse_input(&mut self, state: ElementState, button: MouseButton) {
@@ -114,51 +184,102 @@ impl ControllerTrait for Controller {
         }
     }
 
-    fn keyboard_input(&mut self, key: KeyEvent) {
-        if !key.state.is_pressed() {
-            return;
+    fn touch(&mut self, id: u64, phase: TouchPhase, position: DVec2) {
+        let position = position.as_vec2();
+        match phase {
+            TouchPhase::Started => self.touches.push((id, position)),
+            TouchPhase::Moved => {
+                let prev_distance = self.pinch_distance();
+                if let Some(touch) = self.touches.iter_mut().find(|(i, _)| *i == id) {
+                    touch.1 = position;
+                }
+                if let (Some(prev), Some(distance)) = (prev_distance, self.pinch_distance()) {
+                    let center = (self.touches[0].1 + self.touches[1].1) / 2.0;
+                    if prev > 0.0 {
+                        self.zoom(distance / prev, center);
+                    }
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => self.touches.retain(|(i, _)| *i != id),
         }
-        match key.logical_key {
-            Key::Character(c) => match c.chars().next().unwrap() {
-                'z' => {}
-                'x' => self.simulation_runner.add_iteration(),
-                _ => {}
-            },
-            Key::Named(NamedKey::Space) => {
-                self.simulation_runner.paused = !self.simulation_runner.paused;
+        // A single finger draws like the left mouse button, two fingers zoom
+        match self.touches.as_slice() {
+            [(_, position)] => {
+                if phase == TouchPhase::Started {
+                    self.prev_cursor = *position;
+                }
+                self.cursor = *position;
+                self.mouse_button_pressed |= 1;
             }
-            _ => {}
+            _ => self.mouse_button_pressed &= !1,
         }
     }
 
-    fn prepare_render(&mut self, offset: Vec2) -> impl bytemuck::NoUninit {
+    fn pinch_gesture(&mut self, delta: f64, _phase: TouchPhase) {
+        self.zoom(1.0 + delta as f32, self.cursor);
+    }
+
+    fn file_dropped(&mut self, file: DroppedFile) {
+        match file.read() {
+            Ok(bytes) => self.load_pattern(&String::from_utf8_lossy(&bytes)),
+            Err(err) => log::warn!("Failed to read {}: {err}", file.name()),
+        }
+    }
+
+    fn paste(&mut self, paste: Paste) {
+        if let Paste::Text(text) = paste {
+            self.load_pattern(&text);
+        }
+    }
+
+    fn key_bindings(&self) -> KeyBindings {
+        KeyBindings::default()
+            .bind(NamedKey::Space, KeyAction::TogglePause)
+            .bind(Key::Character("x".into()), KeyAction::Step)
+    }
+
+    fn prepare_render(&mut self, offset: Vec2, time: FrameTime) -> FragmentConstants {
+        self.time = time;
         let fragment_constants = FragmentConstants {
             size: self.size.into(),
             translate: offset,
-            time: self.start.elapsed().as_secs_f32(),
-            mouse_button_pressed: self.mouse_button_pressed,
+            time: time.total as f32,
+            // Don't paint cells through egui windows
+            mouse_button_pressed: if self.pointer_over_viewport || !self.touches.is_empty() {
+                self.mouse_button_pressed
+            } else {
+                0
+            },
             cursor: self.cursor,
             prev_cursor: self.prev_cursor,
             camera_translate: self.camera.translate,
             camera_zoom: self.camera.zoom,
             debug: self.debug.into(),
+            grid_size: self.cell_grid.size.into(),
         };
         self.prev_cursor = self.cursor;
         fragment_constants
     }
 
-    fn update<F: Fn(UVec3, UVec3, &[u8])>(&mut self, compute: F, allowed_duration: f32) {
+    fn update<F: Fn(ComputeEntry, UVec3, UVec3, &ComputeConstants)>(
+        &mut self,
+        compute: F,
+        allowed_duration: f32,
+        time: FrameTime,
+    ) {
         let start = web_time::Instant::now();
-        for _ in 0..self.simulation_runner.iterations() {
+        for _ in 0..time.steps {
             compute(
-                shared::DIM.extend(1),
+                "main_cs".into(),
+                self.cell_grid.size.extend(1),
                 uvec3(16, 16, 1),
-                bytemuck::bytes_of(&ComputeConstants {
+                &ComputeConstants {
                     size: self.size.into(),
-                    time: self.start.elapsed().as_secs_f32(),
+                    time: time.total as f32,
                     zoom: self.camera.zoom,
                     transition: self.transition.into(),
-                }),
+                    grid_size: self.cell_grid.size.into(),
+                },
             );
             self.transition = !self.transition;
             if start.elapsed().as_secs_f32() > allowed_duration {
@@ -173,10 +294,11 @@ impl ControllerTrait for Controller {
             read_only: false,
             shader_stages: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
             cpu_writable: true,
+            kind: BindingKind::StorageBuffer,
         }]]
     }
 
-    fn receive_buffers(&mut self, mut buffers: Vec<wgpu::Buffer>) {
+    fn receive_buffers(&mut self, mut buffers: Vec<Arc<wgpu::Buffer>>) {
         debug_assert!(buffers.len() == 1);
         self.buffer = Some(buffers.swap_remove(0));
     }
@@ -184,31 +306,77 @@ impl ControllerTrait for Controller {
     fn ui(
         &mut self,
         ctx: &egui::Context,
-        _ui_state: &mut UiState,
+        ui_state: &mut UiState,
         graphics_context: &easy_shader_runner::GraphicsContext,
     ) {
+        let upload_grid = std::mem::take(&mut self.upload_grid);
         egui::Window::new("Options")
             .resizable(false)
             .show(ctx, |ui| {
                 ui.add(egui::Label::new(" Simulation Speed").selectable(false));
-                ui.add(
-                    egui::Slider::new(&mut self.simulation_runner.speed, 0.01..=99.0)
-                        .logarithmic(true)
-                        .max_decimals(2),
-                );
-                ui.checkbox(&mut self.simulation_runner.paused, "Paused");
+                let mut speed = ui_state.clock().speed();
+                if ui
+                    .add(
+                        egui::Slider::new(&mut speed, 0.01..=99.0)
+                            .logarithmic(true)
+                            .max_decimals(2),
+                    )
+                    .changed()
+                {
+                    ui_state.clock_mut().set_speed(speed);
+                }
+                ui.add(egui::Label::new(" Grid Size").selectable(false));
+                let mut grid_size = self.pending_grid_size.unwrap_or(self.cell_grid.size.x);
+                let response =
+                    ui.add(egui::Slider::new(&mut grid_size, 16..=1024).logarithmic(true));
+                if response.changed() {
+                    self.pending_grid_size = Some(grid_size);
+                }
+                // Reallocating on every step of a drag would stall the app
+                if !response.dragged() && !response.has_focus() {
+                    if let Some(grid_size) = self.pending_grid_size.take() {
+                        if grid_size != self.cell_grid.size.x {
+                            self.cell_grid = seeded_grid(UVec2::splat(grid_size));
+                            self.transition = false;
+                            ui_state.reallocate_buffer(0, 0);
+                        }
+                    }
+                }
+                let mut paused = ui_state.clock().is_paused();
+                if ui.checkbox(&mut paused, "Paused").changed() {
+                    ui_state.clock_mut().set_paused(paused);
+                }
                 ui.checkbox(&mut self.debug, "Debug");
-                if ui.button("Reset").clicked() {
+                ui.checkbox(&mut ui_state.show_profiler, "Profiler");
+                if ui.button("Reset").clicked() || upload_grid {
                     graphics_context.queue.write_buffer(
                         self.buffer.as_ref().unwrap(),
                         0,
                         bytemuck::cast_slice(&self.cell_grid.buffer),
                     );
                 }
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Screenshot").clicked() {
+                    ui_state.screenshot(false);
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let mut recording = ui_state.is_recording();
+                    if ui.checkbox(&mut recording, "Record").changed() {
+                        if recording {
+                            let options = RecordingOptions::png_sequence("recording");
+                            if let Err(err) = ui_state.start_recording(options) {
+                                log::warn!("Failed to start recording: {err}");
+                            }
+                        } else {
+                            ui_state.stop_recording();
+                        }
+                    }
+                }
                 if self.debug {
                     egui::Grid::new("debug_grid").show(ui, |ui| {
                         ui.label("Elapsed");
-                        ui.label(format!("{:.1}s", self.start.elapsed().as_secs_f64()));
+                        ui.label(format!("{:.1}s", self.time.total));
                         ui.end_row();
 
                         ui.label("Zoom");
@@ -219,6 +387,7 @@ impl ControllerTrait for Controller {
                         ui.label(format!("{:.2}", self.camera.translate));
                         ui.end_row();
                     });
+                    ui.add(ui_state.frame_time_graph());
                 }
             });
     }
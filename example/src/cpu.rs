@@ -0,0 +1,41 @@
+use crate::controller::Controller;
+use easy_shader_runner::{CpuBindings, CpuShader};
+use glam::*;
+use shared::push_constants::shader::*;
+
+/// The shader crate compiled for the host, for [`easy_shader_runner::CpuRunner`]
+pub struct Shader;
+
+impl CpuShader<Controller> for Shader {
+    fn fragment(
+        &self,
+        _entry: &str,
+        frag_coord: Vec4,
+        constants: &FragmentConstants,
+        bindings: &mut CpuBindings,
+    ) -> Vec4 {
+        let mut output = Vec4::ZERO;
+        shader::main_fs(
+            frag_coord,
+            constants,
+            bindings.storage_mut(0, 0),
+            &mut output,
+        );
+        output
+    }
+
+    fn compute(
+        &self,
+        entry: &str,
+        global_invocation_id: UVec3,
+        constants: &ComputeConstants,
+        bindings: &mut CpuBindings,
+    ) {
+        match entry {
+            "main_cs" => {
+                shader::main_cs(global_invocation_id, constants, bindings.storage_mut(0, 0))
+            }
+            _ => unreachable!("Unknown compute entry point `{entry}`"),
+        }
+    }
+}
@@ -1,28 +1,52 @@
+use easy_shader_runner::RunnerConfig;
 use structopt::StructOpt;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::wasm_bindgen::{self, prelude::*};
 
 mod controller;
+#[cfg(all(feature = "cpu", not(target_arch = "wasm32")))]
+pub mod cpu;
 
-#[derive(StructOpt, Clone, Copy)]
+pub use controller::Controller;
+
+#[derive(StructOpt, Clone, Default)]
 pub struct Options {
-    /// Starts in debug mode and with speed set to 0
+    /// Starts in debug mode and paused
     #[structopt(short, long)]
     debug: bool,
+    /// Record input to this file, best with --debug so it starts paused
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    #[structopt(long)]
+    record_input: Option<std::path::PathBuf>,
+    /// Replay input recorded with --record-input
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    #[structopt(long)]
+    replay_input: Option<std::path::PathBuf>,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub fn main() {
     let options = Options::from_args();
     let controller = controller::Controller::new(&options);
+    let config = RunnerConfig::default()
+        .title("easy-shader-runner")
+        // One generation per timestep at speed 1
+        .timestep(1.0 / 30.0)
+        .paused(options.debug);
+    #[cfg(all(feature = "input-recording", not(target_arch = "wasm32")))]
+    let config = match (options.record_input, options.replay_input) {
+        (_, Some(path)) => config.replay_input(path),
+        (Some(path), None) => config.record_input(path),
+        (None, None) => config,
+    };
     cfg_if::cfg_if! {
         if #[cfg(all(
             any(feature = "hot-reload-shader", feature = "runtime-compilation"),
             not(target_arch = "wasm32")
         ))] {
-            easy_shader_runner::run_with_runtime_compilation(controller, "shader/shader");
+            easy_shader_runner::run_with_runtime_compilation(controller, "shader/shader", config);
         } else {
-            easy_shader_runner::run_with_prebuilt_shader(controller, include_bytes!(env!("shader.spv")));
+            easy_shader_runner::run_with_prebuilt_shader(controller, include_bytes!(env!("shader.spv")), config);
         }
     }
 }
@@ -0,0 +1,104 @@
+//! Renders the example headlessly and on the CPU and compares against `tests/golden`
+//! Run with `UPDATE_GOLDEN=1` to accept new output after changing the shader on purpose
+//! The GPU needs the SPIR-V built ahead of time, which only happens without `hot-reload-shader`,
+//! so CI runs `cargo test -p example --no-default-features --features cpu` to check both backends
+#![cfg(any(
+    feature = "cpu",
+    not(any(feature = "hot-reload-shader", feature = "runtime-compilation"))
+))]
+
+use easy_shader_runner::{Golden, RunnerConfig};
+use example::{Controller, Options};
+use shared::DIM;
+
+/// Two frames per generation, since every generation takes a counting and a transition step
+const FRAMES: u32 = 100;
+
+fn golden() -> Golden {
+    Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+        .output_dir(env!("CARGO_TARGET_TMPDIR"))
+}
+
+#[cfg(feature = "cpu")]
+fn cpu_runner() -> easy_shader_runner::CpuRunner<Controller, example::cpu::Shader> {
+    easy_shader_runner::CpuRunner::new(
+        Controller::new(&Options::default()),
+        example::cpu::Shader,
+        DIM,
+        RunnerConfig::default(),
+    )
+}
+
+#[cfg(feature = "cpu")]
+fn live_cells(runner: &impl easy_shader_runner::GoldenRunner) -> Vec<(u32, u32)> {
+    use shared::CellState;
+    let cells: Vec<CellState> = runner
+        .read_buffer(0, 0)
+        .chunks_exact(4)
+        .map(bytemuck::checked::pod_read_unaligned)
+        .collect();
+    (0..DIM.y)
+        .flat_map(|y| (0..DIM.x).map(move |x| (x, y)))
+        .filter(|&(x, y)| matches!(cells[(y * DIM.x + x) as usize], CellState::On))
+        .collect()
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn r_pentomino_cpu() {
+    use easy_shader_runner::GoldenRunner;
+    let golden = golden();
+    let mut runner = cpu_runner();
+    golden
+        .check_frames("r_pentomino", &mut runner, FRAMES)
+        .unwrap_or_else(|err| panic!("{err}"));
+    golden
+        .check_buffer("r_pentomino", &runner.read_buffer(0, 0))
+        .unwrap_or_else(|err| panic!("{err}"));
+}
+
+#[cfg(feature = "cpu")]
+#[test]
+fn blinker_oscillates() {
+    use shared::CellState;
+    let mut runner = cpu_runner();
+    let cells = runner.bindings_mut().storage_mut::<CellState>(0, 0);
+    cells.fill(CellState::Off);
+    for x in 9..12 {
+        cells[(10 * DIM.x + x) as usize] = CellState::On;
+    }
+    runner.render_frame();
+    runner.render_frame();
+    assert_eq!(live_cells(&runner), [(10, 9), (10, 10), (10, 11)]);
+    runner.render_frame();
+    runner.render_frame();
+    assert_eq!(live_cells(&runner), [(9, 10), (10, 10), (11, 10)]);
+}
+
+/// Shares the reference with the CPU, so the two backends are checked against each other
+/// Runs whenever the SPIR-V is built ahead of time, e.g. `cargo test -p example --no-default-features`
+#[cfg(not(any(feature = "hot-reload-shader", feature = "runtime-compilation")))]
+#[test]
+fn r_pentomino_gpu() {
+    use easy_shader_runner::{wgpu, HeadlessRunner};
+    if wgpu::Instance::default()
+        .enumerate_adapters(wgpu::Backends::all())
+        .is_empty()
+    {
+        eprintln!("Skipped, no GPU or software adapter available");
+        return;
+    }
+    let golden = golden().tolerance(1);
+    let mut runner = HeadlessRunner::new(
+        Controller::new(&Options::default()),
+        include_bytes!(env!("shader.spv")),
+        DIM,
+        RunnerConfig::default(),
+    );
+    golden
+        .check_frames("r_pentomino", &mut runner, FRAMES)
+        .unwrap_or_else(|err| panic!("{err}"));
+    golden
+        .check_buffer("r_pentomino", &runner.read_buffer(0, 0))
+        .unwrap_or_else(|err| panic!("{err}"));
+}